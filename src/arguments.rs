@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
+
+use crate::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::game::game_mode::GameMode;
+
+/// Rusty Corks: avoid the cars on the lanes of the road as long as you can
+#[derive(Parser)]
+#[command(version)]
+pub struct Arguments {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Width of the window in pixels
+    #[arg(long, default_value_t = WINDOW_WIDTH as u32, value_parser = clap::value_parser!(u32).range(320..))]
+    pub width: u32,
+
+    /// Height of the window in pixels
+    #[arg(long, default_value_t = WINDOW_HEIGHT as u32, value_parser = clap::value_parser!(u32).range(180..))]
+    pub height: u32,
+
+    /// Starts the game in fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Configuration file to use instead of the one of the user config directory
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Asset pack replacing the assets, by name in the pack directories or by path
+    #[arg(long, value_name = "NAME|PATH")]
+    pub asset_pack: Option<String>,
+
+    /// Seed of the runs, to get the same traffic again
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Speed at the start of a run, instead of the one of the configuration
+    #[arg(long, value_parser = parse_start_speed)]
+    pub start_speed: Option<f32>,
+
+    /// Rule deciding when a run ends, instead of the one of the configuration
+    #[arg(long)]
+    pub mode: Option<GameMode>,
+
+    /// Starts with the sounds muted
+    #[arg(long)]
+    pub mute: bool,
+
+    /// Hand-made level to play: its cars, speeds and events replace the random traffic
+    #[arg(long, value_name = "FILE")]
+    pub level: Option<PathBuf>,
+
+    /// Recorded run to play again
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Plays RUNS runs without window with an automatic driver and reports the speed of the simulation,
+    /// with --replay the replay is checked instead
+    #[arg(long, value_name = "RUNS", num_args = 0..=1, default_missing_value = "10")]
+    pub benchmark: Option<u32>,
+
+    /// Reloads the assets and the configuration when their files change
+    #[arg(long)]
+    pub dev: bool,
+
+    /// Verbosity of the log: off, error, warn, info, debug or trace
+    #[arg(long, value_name = "LEVEL", value_parser = parse_log_level)]
+    pub log_level: Option<LevelFilter>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Detects the tempo, the beats and the loud parts of a wav or ogg music and writes them in a beat map
+    /// next to it (track.beats.toml for track.ogg), the rhythm mode reads it when the tempo is not declared
+    Analyze {
+        /// Music file to analyze
+        music: PathBuf,
+
+        /// Beat map file to write instead of the one next to the music
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Writes a first level of a wav or ogg music from its beat map (or from its analysis without it),
+    /// next to it (track.level.toml for track.ogg), to be completed by hand
+    CreateLevel {
+        /// Music of the level
+        music: PathBuf,
+
+        /// Level file to write instead of the one next to the music
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+fn parse_start_speed(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("expects a positive speed, got \"{}\"", value)),
+    }
+}
+
+fn parse_log_level(value: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(value).map_err(|_| format!("expects off, error, warn, info, debug or trace, got \"{}\"", value))
+}
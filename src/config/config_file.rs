@@ -0,0 +1,493 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use macroquad::input::KeyCode;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{DIFFICULTY_CURVE, DISTANCE_BETWEEN_CARS, GAMEPAD_DEAD_ZONE, KEY_GAME, LANE_CHANGE_DURATION, LANE_POSITIONS, PIXEL_COLLISION, RESERVED_KEYS, ROAD_BOTTOM, ROAD_TOP, SOUND_ASSET_FOR_SOUND_TYPE};
+use crate::game::car::PLAYER_CAR_HEIGHT;
+use crate::game::difficulty::{DifficultyCurve, SpeedCurve};
+use crate::game::game_mode::GameMode;
+use crate::game::rhythm::Tempo;
+use crate::game::sounds::sounds_manager::SoundType;
+use crate::keyboard::{GAME_ACTION, GameAction, get_key_codes_from_game_action, get_str_from_key_code};
+use crate::utils::data_directory::get_config_directory;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+const CONFIG_FILE_HEADER: &str = "\
+# Rusty Corks configuration
+# The missing values take their default value, delete this file to restore all the defaults
+# Key names: A to Z, Key0 to Key9, Space, Escape, Enter, Up, Down, Left, Right, F1 to F25...
+# An action can have several keys: up = [\"Z\", \"Up\"]
+
+";
+
+/// Settings of the game, loaded from a TOML file at startup so they can be changed without recompiling
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub game: GameConfig,
+    pub keys: KeysConfig,
+    pub gamepad: GamepadConfig,
+    pub sounds: SoundsConfig,
+    pub assets: AssetsConfig,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    // More the value is high, more the distance is (between 0 and 1)
+    pub distance_between_cars: f32,
+    pub difficulty: DifficultyCurve,
+    pub mode: GameMode,
+    // Vertical position of each lane from the top of the road, in pixels of the 1280x720 game screen
+    pub lanes: Vec<f32>,
+    // Duration of a lane change in seconds, 0 to change lane instantly
+    pub lane_change_duration: f32,
+    // The cars collide on their opaque pixels instead of their hitbox rectangles
+    pub pixel_collision: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    #[serde(with = "key_codes_format")]
+    pub up: Vec<KeyCode>,
+    #[serde(with = "key_codes_format")]
+    pub down: Vec<KeyCode>,
+    #[serde(with = "key_codes_format")]
+    pub pause_resume: Vec<KeyCode>,
+    #[serde(with = "key_codes_format")]
+    pub quit: Vec<KeyCode>,
+    #[serde(with = "key_codes_format")]
+    pub mute: Vec<KeyCode>,
+    #[serde(with = "key_codes_format")]
+    pub high_scores: Vec<KeyCode>,
+    #[serde(with = "key_codes_format")]
+    pub settings: Vec<KeyCode>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamepadConfig {
+    pub enabled: bool,
+    pub dead_zone: f32,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SoundsConfig {
+    pub muted: bool,
+    pub menu: SoundConfig,
+    pub game: SoundConfig,
+    pub game_over: SoundConfig,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SoundConfig {
+    // Replaces the file of the asset manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub volume: f32,
+    // Replaces the tempo of the asset manifest, used by the rhythm mode for the game music
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tempo: Option<Tempo>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetsConfig {
+    // Name of a pack of the pack directories, or path of a pack, the default assets without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack: Option<String>,
+}
+
+impl Config {
+    pub fn get_default_path() -> PathBuf {
+        get_config_directory().join(CONFIG_FILE_NAME)
+    }
+
+    // Loads the configuration file, if it does not exist it is created with the default values
+    // to let the player find and edit it
+    pub fn load(path: &Path) -> RustyResult<Config> {
+        if !path.exists() {
+            let config = Config::default();
+            if let Err(e) = config.save(path) {
+                warn!("Error creating the default configuration: {}", e);
+            }
+            return Ok(config);
+        }
+
+        let content = fs::read_to_string(path).map_err(RustyError::file("read the configuration", path))?;
+
+        // The TOML errors give the line and the column of the problem
+        let config: Config = toml::from_str(&content).map_err(|e| RustyError::Config(
+            format!("{}: {}", path.display(), e)
+        ))?;
+
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(RustyError::Config(
+                format!("{}:\n  - {}", path.display(), problems.join("\n  - "))
+            ));
+        }
+
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> RustyResult<()> {
+        let write_error = |e: String| RustyError::Config(
+            format!("Impossible to write {}: {}", path.display(), e)
+        );
+
+        let mut value = toml::Value::try_from(self).map_err(|e| write_error(e.to_string()))?;
+        Config::round_floats(&mut value);
+        let content = toml::to_string(&value).map_err(|e| write_error(e.to_string()))?;
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(RustyError::file("create the configuration directory", directory))?;
+        }
+
+        fs::write(path, format!("{}{}", CONFIG_FILE_HEADER, content)).map_err(RustyError::file("write the configuration", path))
+    }
+
+    // Every problem of the file is reported at once, the player does not have to fix them one by one
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !(self.game.distance_between_cars > 0.0 && self.game.distance_between_cars <= 1.0) {
+            problems.push("game.distance_between_cars must be between 0 (excluded) and 1".to_string());
+        }
+
+        // The speed never goes down, the cars would stop then go backwards
+        let difficulty = &self.game.difficulty;
+        let (start, increase_name, increase) = match difficulty.speed_curve {
+            SpeedCurve::Linear { start, increase_per_second } => (start, "increase_per_second", increase_per_second),
+            SpeedCurve::Stepped { start, increase, interval } => {
+                if !(interval > 0.0 && interval.is_finite()) {
+                    problems.push("game.difficulty.speed_curve.interval must be positive".to_string());
+                }
+                (start, "increase", increase)
+            }
+            SpeedCurve::Logarithmic { start, factor, time_scale } => {
+                if !(time_scale > 0.0 && time_scale.is_finite()) {
+                    problems.push("game.difficulty.speed_curve.time_scale must be positive".to_string());
+                }
+                (start, "factor", factor)
+            }
+        };
+        if !(start > 0.0 && start.is_finite()) {
+            problems.push("game.difficulty.speed_curve.start must be positive".to_string());
+        }
+        if !(increase >= 0.0 && increase.is_finite()) {
+            problems.push(format!("game.difficulty.speed_curve.{} must be positive or zero", increase_name));
+        }
+        if let Some(max_speed) = difficulty.max_speed {
+            if !(max_speed > 0.0 && max_speed.is_finite()) {
+                problems.push("game.difficulty.max_speed must be positive".to_string());
+            } else if max_speed < start {
+                problems.push("game.difficulty.max_speed must be greater than the start speed".to_string());
+            }
+        }
+
+        if self.game.lanes.len() < 2 {
+            problems.push("game.lanes needs at least 2 lanes".to_string());
+        }
+        // The cars of two lanes must not overlap, and every car must be on the road
+        let lane_range = ROAD_TOP + PLAYER_CAR_HEIGHT / 2.0..=ROAD_BOTTOM - PLAYER_CAR_HEIGHT / 2.0;
+        if self.game.lanes.iter().any(|position| !lane_range.contains(position)) {
+            problems.push(format!("game.lanes must be between {} and {}", lane_range.start(), lane_range.end()));
+        }
+        if self.game.lanes.windows(2).any(|lanes| lanes[1] - lanes[0] < PLAYER_CAR_HEIGHT) {
+            problems.push(format!("game.lanes must go from the top to the bottom, with at least {} pixels between two lanes",
+                                  PLAYER_CAR_HEIGHT));
+        }
+
+        if !(0.0..=1.0).contains(&self.game.lane_change_duration) {
+            problems.push("game.lane_change_duration must be between 0 and 1".to_string());
+        }
+
+        // Every action needs a key, and a key can only do one action
+        let key_bindings = self.keys.get_key_bindings();
+        for &(action, _) in &GAME_ACTION {
+            if get_key_codes_from_game_action(&key_bindings, action).is_empty() {
+                problems.push(format!("keys.{} has no key", KeysConfig::get_field_name(action)));
+            }
+        }
+        for (index, &(key, action)) in key_bindings.iter().enumerate() {
+            for &(reserved_key, description) in &RESERVED_KEYS {
+                if key == reserved_key {
+                    problems.push(format!("the key {} of keys.{} {}",
+                                          get_str_from_key_code(key),
+                                          KeysConfig::get_field_name(action),
+                                          description));
+                }
+            }
+            for &(other_key, other_action) in &key_bindings[index + 1..] {
+                if key != other_key {
+                    continue;
+                }
+                if action == other_action {
+                    problems.push(format!("the key {} is written twice in keys.{}",
+                                          get_str_from_key_code(key),
+                                          KeysConfig::get_field_name(action)));
+                } else {
+                    problems.push(format!("the key {} is used by keys.{} and keys.{}",
+                                          get_str_from_key_code(key),
+                                          KeysConfig::get_field_name(action),
+                                          KeysConfig::get_field_name(other_action)));
+                }
+            }
+        }
+
+        if !(0.0..1.0).contains(&self.gamepad.dead_zone) {
+            problems.push("gamepad.dead_zone must be between 0 and 1 (excluded)".to_string());
+        }
+
+        for (sound_type, sound) in self.sounds.get_sounds() {
+            let name = SoundsConfig::get_field_name(sound_type);
+            if sound.path.as_ref().is_some_and(|path| path.trim().is_empty()) {
+                problems.push(format!("sounds.{}.path is empty", name));
+            }
+            if !(0.0..=2.0).contains(&sound.volume) {
+                problems.push(format!("sounds.{}.volume must be between 0 and 2", name));
+            }
+            if let Some(problem) = sound.tempo.as_ref().and_then(Tempo::check) {
+                problems.push(format!("sounds.{}.tempo: {}", name, problem));
+            }
+        }
+
+        problems
+    }
+
+    // The f32 values become f64 in TOML (0.4 would be written 0.4000000059604645),
+    // they are rounded back to the shortest decimal value of the f32
+    fn round_floats(value: &mut toml::Value) {
+        match value {
+            toml::Value::Float(float) => {
+                *float = (*float as f32).to_string().parse().unwrap_or(*float);
+            }
+            toml::Value::Table(table) => {
+                for (_, value) in table.iter_mut() {
+                    Config::round_floats(value);
+                }
+            }
+            toml::Value::Array(array) => {
+                for value in array.iter_mut() {
+                    Config::round_floats(value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig {
+            distance_between_cars: DISTANCE_BETWEEN_CARS,
+            difficulty: DIFFICULTY_CURVE,
+            mode: GameMode::default(),
+            lanes: LANE_POSITIONS.to_vec(),
+            lane_change_duration: LANE_CHANGE_DURATION,
+            pixel_collision: PIXEL_COLLISION,
+        }
+    }
+}
+
+impl KeysConfig {
+    pub fn from_key_bindings(key_bindings: &[(KeyCode, GameAction)]) -> KeysConfig {
+        KeysConfig {
+            up: get_key_codes_from_game_action(key_bindings, GameAction::Up),
+            down: get_key_codes_from_game_action(key_bindings, GameAction::Down),
+            pause_resume: get_key_codes_from_game_action(key_bindings, GameAction::PauseResume),
+            quit: get_key_codes_from_game_action(key_bindings, GameAction::Quit),
+            mute: get_key_codes_from_game_action(key_bindings, GameAction::Mute),
+            high_scores: get_key_codes_from_game_action(key_bindings, GameAction::HighScores),
+            settings: get_key_codes_from_game_action(key_bindings, GameAction::Settings),
+        }
+    }
+
+    pub fn get_key_bindings(&self) -> Vec<(KeyCode, GameAction)> {
+        let keys_for_action = [
+            (&self.up, GameAction::Up),
+            (&self.down, GameAction::Down),
+            (&self.pause_resume, GameAction::PauseResume),
+            (&self.quit, GameAction::Quit),
+            (&self.mute, GameAction::Mute),
+            (&self.high_scores, GameAction::HighScores),
+            (&self.settings, GameAction::Settings),
+        ];
+
+        let mut key_bindings = Vec::new();
+        for (keys, action) in keys_for_action {
+            for &key in keys {
+                key_bindings.push((key, action));
+            }
+        }
+        key_bindings
+    }
+
+    fn get_field_name(game_action: GameAction) -> &'static str {
+        match game_action {
+            GameAction::Up => "up",
+            GameAction::Down => "down",
+            GameAction::PauseResume => "pause_resume",
+            GameAction::Quit => "quit",
+            GameAction::Mute => "mute",
+            GameAction::HighScores => "high_scores",
+            GameAction::Settings => "settings",
+            GameAction::None => "none",
+        }
+    }
+}
+
+impl Default for KeysConfig {
+    fn default() -> KeysConfig {
+        KeysConfig::from_key_bindings(&KEY_GAME)
+    }
+}
+
+impl Default for GamepadConfig {
+    fn default() -> GamepadConfig {
+        GamepadConfig {
+            enabled: true,
+            dead_zone: GAMEPAD_DEAD_ZONE,
+        }
+    }
+}
+
+impl SoundsConfig {
+    pub fn get_sounds(&self) -> [(SoundType, &SoundConfig); 3] {
+        [
+            (SoundType::Menu, &self.menu),
+            (SoundType::Game, &self.game),
+            (SoundType::GameOver, &self.game_over),
+        ]
+    }
+
+    fn get_field_name(sound_type: SoundType) -> &'static str {
+        match sound_type {
+            SoundType::Menu => "menu",
+            SoundType::Game => "game",
+            SoundType::GameOver => "game_over",
+        }
+    }
+
+    fn get_default_sound(sound_type: SoundType) -> SoundConfig {
+        let mut sound = SoundConfig {
+            path: None,
+            volume: 1.0,
+            tempo: None,
+        };
+        for &(default_type, _, volume) in &SOUND_ASSET_FOR_SOUND_TYPE {
+            if default_type == sound_type {
+                sound.volume = volume;
+            }
+        }
+        sound
+    }
+}
+
+impl Default for SoundsConfig {
+    fn default() -> SoundsConfig {
+        SoundsConfig {
+            muted: false,
+            menu: SoundsConfig::get_default_sound(SoundType::Menu),
+            game: SoundsConfig::get_default_sound(SoundType::Game),
+            game_over: SoundsConfig::get_default_sound(SoundType::GameOver),
+        }
+    }
+}
+
+// The keys are written with their name ("Space", "Z"...) instead of their code,
+// a single name is accepted for an action with only one key
+mod key_codes_format {
+    use macroquad::input::KeyCode;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::ser::SerializeSeq;
+
+    use crate::keyboard::{get_key_code_from_str, get_str_from_key_code};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum KeyNames {
+        One(String),
+        Several(Vec<String>),
+    }
+
+    pub fn serialize<S: Serializer>(key_codes: &[KeyCode], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut sequence = serializer.serialize_seq(Some(key_codes.len()))?;
+        for key_code in key_codes {
+            sequence.serialize_element(&get_str_from_key_code(*key_code))?;
+        }
+        sequence.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<KeyCode>, D::Error> {
+        let names = match KeyNames::deserialize(deserializer)? {
+            KeyNames::One(name) => vec![name],
+            KeyNames::Several(names) => names,
+        };
+
+        let mut key_codes = Vec::new();
+        for name in names {
+            match get_key_code_from_str(&name) {
+                Some(key_code) if key_code != KeyCode::Unknown => key_codes.push(key_code),
+                _ => return Err(serde::de::Error::custom(format!("unknown key \"{}\"", name))),
+            }
+        }
+        Ok(key_codes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_lane_problems(lanes: &[f32]) -> Vec<String> {
+        let mut config = Config::default();
+        config.game.lanes = lanes.to_vec();
+        config.validate()
+    }
+
+    #[test]
+    fn lanes_must_be_on_the_road() {
+        assert!(get_lane_problems(&LANE_POSITIONS).is_empty());
+        assert!(get_lane_problems(&[260.0, 460.0]).is_empty());
+        assert!(get_lane_problems(&[190.0, 275.0, 360.0, 445.0, 530.0]).is_empty());
+
+        // Out of the asphalt of the road
+        assert_eq!(get_lane_problems(&[120.0, 240.0, 360.0, 480.0, 600.0]), ["game.lanes must be between 186.5 and 533.5"]);
+        assert_eq!(get_lane_problems(&[360.0]), ["game.lanes needs at least 2 lanes"]);
+        assert_eq!(get_lane_problems(&[200.0, 250.0]).len(), 1);
+        assert_eq!(get_lane_problems(&[460.0, 260.0]).len(), 1);
+    }
+
+    fn get_difficulty_problems(speed_curve: SpeedCurve, max_speed: Option<f32>) -> Vec<String> {
+        let mut config = Config::default();
+        config.game.difficulty = DifficultyCurve { speed_curve, max_speed };
+        config.validate()
+    }
+
+    #[test]
+    fn speed_never_goes_down() {
+        assert!(get_difficulty_problems(SpeedCurve::Linear { start: 300.0, increase_per_second: 0.0 }, Some(900.0)).is_empty());
+
+        assert_eq!(get_difficulty_problems(SpeedCurve::Linear { start: 300.0, increase_per_second: -5.0 }, None),
+                   ["game.difficulty.speed_curve.increase_per_second must be positive or zero"]);
+        assert_eq!(get_difficulty_problems(SpeedCurve::Stepped { start: 300.0, increase: -50.0, interval: 10.0 }, None),
+                   ["game.difficulty.speed_curve.increase must be positive or zero"]);
+        assert_eq!(get_difficulty_problems(SpeedCurve::Logarithmic { start: 300.0, factor: -100.0, time_scale: 30.0 }, None),
+                   ["game.difficulty.speed_curve.factor must be positive or zero"]);
+        assert_eq!(get_difficulty_problems(SpeedCurve::Linear { start: -300.0, increase_per_second: 5.0 }, None),
+                   ["game.difficulty.speed_curve.start must be positive"]);
+        assert_eq!(get_difficulty_problems(SpeedCurve::Linear { start: 300.0, increase_per_second: 5.0 }, Some(0.0)),
+                   ["game.difficulty.max_speed must be positive"]);
+        assert_eq!(get_difficulty_problems(SpeedCurve::Linear { start: 300.0, increase_per_second: f32::NAN }, None).len(), 1);
+    }
+}
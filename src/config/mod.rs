@@ -0,0 +1,128 @@
+use gilrs::{Axis, Button};
+use macroquad::prelude::KeyCode;
+
+use crate::game::assets::asset_manager::{GAME_MUSIC, GAME_OVER_SOUND, MENU_MUSIC};
+use crate::game::difficulty::{DifficultyCurve, SpeedCurve};
+use crate::game::sounds::sounds_manager::SoundType;
+use crate::keyboard::GameAction;
+use crate::keyboard::input::{AxisDirection, Input};
+
+pub mod config_file;
+
+// The values of this file are the defaults of the configuration file (see config_file.rs),
+// the player can change them without recompiling the game
+
+// Game settings
+pub const START_GAME_SPEED: f32 = 500.0;
+pub const SPEED_INCREASE_VALUE: f32 = 1.0;
+// In seconds of game, the time spent in pause is not counted
+pub const SPEED_INCREASE_INTERVAL: f32 = 0.1;
+
+// The speed curve can be linear, stepped or logarithmic, and capped with a max speed
+pub const DIFFICULTY_CURVE: DifficultyCurve = DifficultyCurve {
+    speed_curve: SpeedCurve::Stepped {
+        start: START_GAME_SPEED,
+        increase: SPEED_INCREASE_VALUE,
+        interval: SPEED_INCREASE_INTERVAL,
+    },
+    max_speed: None,
+};
+
+// The simulation always advances by steps of this duration (in seconds), whatever the frame rate
+pub const SIMULATION_STEP_DURATION: f32 = 1.0 / 120.0;
+// Longest frame simulated, a longer frame (hitch, window moved...) slows down the game instead
+pub const MAX_FRAME_DURATION: f32 = 0.25;
+// Points earned for each pixel travelled
+pub const SCORE_PER_DISTANCE: f32 = 0.3;
+
+// More the value is high, more de distance is
+pub const DISTANCE_BETWEEN_CARS: f32 = 0.4;
+
+// Vertical position of the center of each lane, from the top of the road, in pixels of the 1280x720 game screen
+pub const LANE_POSITIONS: [f32; 3] = [220.0, 360.0, 500.0];
+// Top and bottom of the asphalt of the road texture, the cars of every lane must stay on it
+pub const ROAD_TOP: f32 = 150.0;
+pub const ROAD_BOTTOM: f32 = 570.0;
+// Duration of the move of the player car from a lane to the next one, in seconds (0 is instant)
+pub const LANE_CHANGE_DURATION: f32 = 0.15;
+
+// The cars collide when an opaque pixel of each car overlaps, instead of their hitbox rectangles
+pub const PIXEL_COLLISION: bool = false;
+
+// Number of runs kept in the high score table
+pub const HIGH_SCORE_COUNT: usize = 10;
+
+// Every run is recorded in this directory of the user data directory, it can be played again with `--replay <file>`
+pub const REPLAY_DIRECTORY: &str = "replays";
+// Number of replays kept in the replay directory, the oldest ones are removed
+pub const MAX_REPLAY_COUNT: usize = 50;
+
+// Default keyboard settings, an action can have several keys
+pub const KEY_GAME: [(KeyCode, GameAction); 9] = [
+    (KeyCode::Z, GameAction::Up),
+    (KeyCode::Up, GameAction::Up),
+    (KeyCode::S, GameAction::Down),
+    (KeyCode::Down, GameAction::Down),
+    (KeyCode::Space, GameAction::PauseResume),
+    (KeyCode::Escape, GameAction::Quit),
+    (KeyCode::M, GameAction::Mute),
+    (KeyCode::H, GameAction::HighScores),
+    (KeyCode::Tab, GameAction::Settings),
+];
+
+// Default gamepad settings, the D-pad and the left stick change the lane
+pub const GAMEPAD_GAME: [(Input, GameAction); 9] = [
+    (Input::GamepadButton(Button::DPadUp), GameAction::Up),
+    (Input::GamepadButton(Button::DPadDown), GameAction::Down),
+    // Some gamepads give their D-pad as an axis
+    (Input::GamepadAxis(Axis::DPadY, AxisDirection::Positive), GameAction::Up),
+    (Input::GamepadAxis(Axis::DPadY, AxisDirection::Negative), GameAction::Down),
+    (Input::GamepadAxis(Axis::LeftStickY, AxisDirection::Positive), GameAction::Up),
+    (Input::GamepadAxis(Axis::LeftStickY, AxisDirection::Negative), GameAction::Down),
+    (Input::GamepadButton(Button::Start), GameAction::PauseResume),
+    (Input::GamepadButton(Button::Select), GameAction::Quit),
+    (Input::GamepadButton(Button::North), GameAction::HighScores),
+];
+// Part of the stick course ignored (between 0 and 1), a worn stick does not move the car by itself
+pub const GAMEPAD_DEAD_ZONE: f32 = 0.5;
+
+// Toggles the fullscreen on every screen, it cannot be bound to an action
+pub const FULLSCREEN_KEY: KeyCode = KeyCode::F11;
+// Shows the hitboxes of the cars on every screen, it cannot be bound to an action
+pub const HITBOX_OVERLAY_KEY: KeyCode = KeyCode::F3;
+// Keys which cannot be bound to an action, with what they do
+pub const RESERVED_KEYS: [(KeyCode, &str); 2] = [
+    (FULLSCREEN_KEY, "toggles the fullscreen"),
+    (HITBOX_OVERLAY_KEY, "shows the hitboxes"),
+];
+
+// Duration of a run in the time attack mode, in seconds
+pub const TIME_ATTACK_DURATION: f32 = 60.0;
+// Rhythm mode: beats in a bar of the music, a bar starts with one more car
+pub const BEATS_PER_BAR: u64 = 4;
+// A lane change closer to a beat than this time, in seconds, is on the beat
+pub const ON_BEAT_TOLERANCE: f32 = 0.08;
+// Points given for a lane change on the beat
+pub const ON_BEAT_BONUS: f32 = 50.0;
+// Tempo used for a music whose tempo is neither declared nor detected
+pub const DEFAULT_BPM: f32 = 120.0;
+// A level created from a beat map has no car on the beats of the parts of the music quieter than this intensity (from 0 to 1)
+pub const QUIET_INTENSITY: f32 = 0.3;
+// A message of a level stays on the screen for this time, in seconds
+pub const LEVEL_MESSAGE_DURATION: f32 = 2.0;
+
+// Every asset of the game is listed in this file, with its path and its fallback
+pub const ASSET_MANIFEST_PATH: &str = "assets/manifest.toml";
+// Directory of the asset packs, next to the game and in the user data directory
+pub const ASSET_PACK_DIRECTORY: &str = "packs";
+// Directory of the default assets, watched in development mode
+pub const ASSET_DIRECTORY: &str = "assets";
+// Seconds between two checks of the watched files in development mode
+pub const DEV_WATCH_INTERVAL: f64 = 0.5;
+
+// Default sounds settings: asset of the manifest and volume of each sound
+pub const SOUND_ASSET_FOR_SOUND_TYPE: [(SoundType, &str, f32); 3] = [
+    (SoundType::Menu, MENU_MUSIC, 1.1),
+    (SoundType::Game, GAME_MUSIC, 0.7),
+    (SoundType::GameOver, GAME_OVER_SOUND, 1.0),
+];
\ No newline at end of file
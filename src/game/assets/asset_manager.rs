@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use image::RgbaImage;
+use log::{debug, info, warn};
+use macroquad::audio::{load_sound_from_bytes, Sound};
+use macroquad::prelude::{Image, PINK, Texture2D};
+
+use crate::game::assets::asset_pack::AssetPack;
+use crate::game::assets::manifest::{AssetEntry, AssetManifest, AssetType, get_file_name};
+use crate::game::car::hitbox::Hitbox;
+use crate::game::rhythm::{BeatTrack, Tempo};
+use crate::game::rhythm::beat_map::BeatMap;
+use crate::game::sounds::audio_file;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+// Names of the assets in the manifest
+pub const ROAD_TEXTURE: &str = "road";
+pub const COLLISION_TEXTURE: &str = "collision";
+pub const PLAYER_CAR_TEXTURE: &str = "player_car";
+pub const BOT_CAR_TEXTURES: &str = "bot_cars";
+pub const MENU_MUSIC: &str = "menu_music";
+pub const GAME_MUSIC: &str = "game_music";
+pub const GAME_OVER_SOUND: &str = "game_over_sound";
+
+// Every asset used by the game, they are all checked at startup
+const GAME_ASSETS: [(&str, AssetType); 7] = [
+    (ROAD_TEXTURE, AssetType::Texture),
+    (COLLISION_TEXTURE, AssetType::Texture),
+    (PLAYER_CAR_TEXTURE, AssetType::Texture),
+    (BOT_CAR_TEXTURES, AssetType::TextureDirectory),
+    (MENU_MUSIC, AssetType::Sound),
+    (GAME_MUSIC, AssetType::Sound),
+    (GAME_OVER_SOUND, AssetType::Sound),
+];
+
+const PLACEHOLDER_TEXTURE_WIDTH: u16 = 100;
+const PLACEHOLDER_TEXTURE_HEIGHT: u16 = 50;
+const PLACEHOLDER_SOUND_SAMPLE_RATE: u32 = 22050;
+
+// Index of a texture in the asset manager, cheap to copy and to store in the game objects
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle(usize);
+
+enum LoadedAsset {
+    Texture(Texture2D),
+    Textures(Vec<Texture2D>),
+    Sound(Sound),
+}
+
+/// Owner of the textures and the sounds of the game: each file is loaded once at startup,
+/// nothing is read from the disk or uploaded to the GPU during a run
+#[derive(Clone, Default)]
+pub struct AssetManager {
+    textures: Vec<Texture2D>,
+    texture_handles: HashMap<String, Vec<TextureHandle>>,
+    sounds: HashMap<String, Sound>,
+}
+
+impl AssetManager {
+    // Every problem of the assets is reported at once, the player does not have to fix them one by one
+    pub async fn load(manifest: &AssetManifest, pack: Option<&AssetPack>) -> RustyResult<AssetManager> {
+        let mut assets = AssetManager::default();
+        let mut problems = Vec::new();
+
+        for (name, asset_type) in GAME_ASSETS {
+            match manifest.get_entry(name) {
+                None => problems.push(format!("{} is not in the manifest", name)),
+                Some(entry) if entry.asset_type != asset_type => {
+                    problems.push(format!("{} must be of type {}", name, AssetManager::get_type_name(asset_type)));
+                }
+                Some(entry) => {
+                    // The files of an entry of the pack are read in the pack
+                    let entry_pack = if entry.in_pack { pack } else { None };
+                    if let Err(problem) = assets.load_entry(name, entry, entry_pack).await {
+                        problems.push(problem);
+                    }
+                }
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(RustyError::Asset(
+                format!("missing or broken assets:\n  - {}", problems.join("\n  - "))
+            ));
+        }
+
+        Ok(assets)
+    }
+
+    pub fn get_texture_handle(&self, name: &str) -> RustyResult<TextureHandle> {
+        self.get_texture_handles(name)?.first().copied()
+            .ok_or_else(|| RustyError::Asset(format!("the texture {} is not loaded", name)))
+    }
+
+    pub fn get_texture_handles(&self, name: &str) -> RustyResult<Vec<TextureHandle>> {
+        self.texture_handles.get(name).cloned()
+            .ok_or_else(|| RustyError::Asset(format!("the texture {} is not loaded", name)))
+    }
+
+    pub fn get_texture(&self, handle: TextureHandle) -> Texture2D {
+        self.textures[handle.0]
+    }
+
+    pub fn get_sound(&self, name: &str) -> RustyResult<Sound> {
+        self.sounds.get(name).copied()
+            .ok_or_else(|| RustyError::Asset(format!("the sound {} is not loaded", name)))
+    }
+
+    // Deletes the textures from the GPU, the asset manager must not be used anymore
+    pub fn delete(&self) {
+        for texture in &self.textures {
+            texture.delete();
+        }
+    }
+
+    // Hitboxes of the textures of an entry, in the order of the textures. The files are decoded
+    // without creating the textures, the simulation without window needs the hitboxes too
+    pub fn load_hitboxes(manifest: &AssetManifest, pack: Option<&AssetPack>, name: &str) -> RustyResult<Vec<Hitbox>> {
+        let entry = manifest.get_entry(name)
+            .ok_or_else(|| RustyError::Asset(format!("{} is not in the manifest", name)))?;
+        let entry_pack = if entry.in_pack { pack } else { None };
+
+        let mut paths = vec![entry.path.as_str()];
+        if let Some(fallback) = &entry.fallback {
+            paths.push(fallback.as_str());
+        }
+
+        let mut errors = Vec::new();
+        for path in paths {
+            match AssetManager::load_file_hitboxes(entry, path, entry_pack) {
+                Ok(hitboxes) => return Ok(hitboxes),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // A missing optional asset is replaced by one placeholder
+        if entry.optional {
+            Ok(vec![Hitbox::from_size(PLACEHOLDER_TEXTURE_WIDTH as u32, PLACEHOLDER_TEXTURE_HEIGHT as u32)])
+        } else {
+            Err(RustyError::Asset(format!("{}: {}", name, errors.join(", "))))
+        }
+    }
+
+    // Beats of the music of an entry, from the tempo of the file played and its duration.
+    // The files are read without creating the sounds, the simulation without window needs the beats too
+    pub fn load_beat_track(manifest: &AssetManifest, pack: Option<&AssetPack>, name: &str) -> RustyResult<BeatTrack> {
+        let entry = manifest.get_entry(name)
+            .ok_or_else(|| RustyError::Asset(format!("{} is not in the manifest", name)))?;
+        let entry_pack = if entry.in_pack { pack } else { None };
+
+        let mut paths = vec![entry.path.as_str()];
+        if let Some(fallback) = &entry.fallback {
+            paths.push(fallback.as_str());
+        }
+
+        let mut errors = Vec::new();
+        for path in paths {
+            match AssetManager::load_file_beat_track(entry, path, entry_pack) {
+                Ok(beat_track) => return Ok(beat_track),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // The placeholder is one second of silence
+        if entry.optional {
+            Ok(BeatTrack::from_tempo(Tempo::default(), 1.0))
+        } else {
+            Err(RustyError::Asset(format!("{}: {}", name, errors.join(", "))))
+        }
+    }
+
+    // The path is tried, then the fallback, then an optional asset gets a placeholder
+    async fn load_entry(&mut self, name: &str, entry: &AssetEntry, pack: Option<&AssetPack>) -> Result<(), String> {
+        let mut paths = vec![entry.path.as_str()];
+        if let Some(fallback) = &entry.fallback {
+            paths.push(fallback.as_str());
+        }
+
+        let mut errors = Vec::new();
+        for path in paths {
+            match AssetManager::load_file(entry.asset_type, path, pack).await {
+                Ok(asset) => {
+                    if errors.is_empty() {
+                        debug!("Asset {} loaded from {}", name, path);
+                    } else {
+                        warn!("Asset {} loaded from {} ({})", name, path, errors.join(", "));
+                    }
+                    self.insert(name, asset);
+                    return Ok(());
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+
+        if entry.optional {
+            warn!("Asset {} replaced by a placeholder ({})", name, errors.join(", "));
+            let placeholder = AssetManager::get_placeholder(entry.asset_type).await?;
+            self.insert(name, placeholder);
+            Ok(())
+        } else {
+            Err(format!("{}: {}", name, errors.join(", ")))
+        }
+    }
+
+    fn insert(&mut self, name: &str, asset: LoadedAsset) {
+        let textures = match asset {
+            LoadedAsset::Texture(texture) => vec![texture],
+            LoadedAsset::Textures(textures) => textures,
+            LoadedAsset::Sound(sound) => {
+                self.sounds.insert(name.to_string(), sound);
+                return;
+            }
+        };
+
+        let mut handles = Vec::new();
+        for texture in textures {
+            handles.push(TextureHandle(self.textures.len()));
+            self.textures.push(texture);
+        }
+        self.texture_handles.insert(name.to_string(), handles);
+    }
+
+    async fn load_file(asset_type: AssetType, path: &str, pack: Option<&AssetPack>) -> Result<LoadedAsset, String> {
+        match asset_type {
+            AssetType::Texture => Ok(LoadedAsset::Texture(AssetManager::load_texture(path, pack)?)),
+            AssetType::TextureDirectory => {
+                let texture_paths = match pack {
+                    Some(pack) => pack.get_texture_paths(path)?,
+                    None => AssetManager::get_texture_paths(path)?,
+                };
+
+                let mut textures = Vec::new();
+                let mut errors = Vec::new();
+                for texture_path in texture_paths {
+                    match AssetManager::load_texture(&texture_path, pack) {
+                        Ok(texture) => textures.push(texture),
+                        Err(e) => errors.push(e),
+                    }
+                }
+
+                if !errors.is_empty() {
+                    return Err(errors.join(", "));
+                }
+                if textures.is_empty() {
+                    return Err(format!("no png file in {}", path));
+                }
+                Ok(LoadedAsset::Textures(textures))
+            }
+            AssetType::Sound => {
+                let bytes = AssetManager::read_file(path, pack)?;
+
+                if !audio_file::is_wav(&bytes) && !audio_file::is_ogg(&bytes) {
+                    return Err(format!("{} is not a wav or ogg file", path));
+                }
+
+                let sound = load_sound_from_bytes(&bytes).await.map_err(|e| format!("{}: {}", path, e))?;
+                Ok(LoadedAsset::Sound(sound))
+            }
+        }
+    }
+
+    fn load_file_hitboxes(entry: &AssetEntry, path: &str, pack: Option<&AssetPack>) -> Result<Vec<Hitbox>, String> {
+        let texture_paths = match (entry.asset_type, pack) {
+            (AssetType::TextureDirectory, Some(pack)) => pack.get_texture_paths(path)?,
+            (AssetType::TextureDirectory, None) => AssetManager::get_texture_paths(path)?,
+            _ => vec![path.to_string()],
+        };
+        if texture_paths.is_empty() {
+            return Err(format!("no png file in {}", path));
+        }
+
+        let mut hitboxes = Vec::new();
+        for texture_path in texture_paths {
+            let declared_rectangle = entry.hitboxes.get(&get_file_name(&texture_path)).copied();
+
+            let image = AssetManager::decode_image(&texture_path, pack)?;
+            let hitbox = Hitbox::from_image(&image, declared_rectangle);
+            let (x, y, width, height) = hitbox.get_rectangle();
+            match declared_rectangle {
+                Some(declared) if declared != [x as u32, y as u32, width as u32, height as u32] => {
+                    warn!("The hitbox {:?} of {} is reduced to the texture of {}x{} pixels",
+                          declared, texture_path, image.width(), image.height());
+                }
+                _ => debug!("Hitbox of {}: {:?}", texture_path, (x, y, width, height)),
+            }
+            hitboxes.push(hitbox);
+        }
+        Ok(hitboxes)
+    }
+
+    // A declared tempo gives regular beats, otherwise the beat map written next to the music by the analysis is read
+    fn load_file_beat_track(entry: &AssetEntry, path: &str, pack: Option<&AssetPack>) -> Result<BeatTrack, String> {
+        let bytes = AssetManager::read_file(path, pack)?;
+        let duration = audio_file::get_duration(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+
+        if let Some(tempo) = entry.tempos.get(&get_file_name(path)) {
+            if let Some(problem) = tempo.check() {
+                return Err(format!("the tempo of {} is wrong: {}", path, problem));
+            }
+            debug!("Tempo of {}: {} BPM, first beat at {} s", path, tempo.bpm, tempo.offset);
+            return Ok(BeatTrack::from_tempo(*tempo, duration));
+        }
+
+        // The music is played even if its beat map is broken, the rhythm mode uses the default tempo
+        let beat_map_path = BeatMap::get_path(path);
+        if AssetManager::has_file(&beat_map_path, pack) {
+            let beat_map = AssetManager::read_file(&beat_map_path, pack)
+                .and_then(|bytes| BeatMap::parse(&String::from_utf8_lossy(&bytes)));
+            match beat_map {
+                Ok(beat_map) => {
+                    debug!("Beat map of {}: {} BPM, {} beats", path, beat_map.bpm, beat_map.beats.len());
+                    return Ok(BeatTrack::from_beat_map(&beat_map));
+                }
+                Err(e) => warn!("The beat map {} is broken, the rhythm mode uses {} BPM: {}", beat_map_path, Tempo::default().bpm, e),
+            }
+        } else {
+            info!("The tempo of {} is unknown, the rhythm mode uses {} BPM (`rusty_corks analyze` detects it)", path, Tempo::default().bpm);
+        }
+
+        Ok(BeatTrack::from_tempo(Tempo::default(), duration))
+    }
+
+    fn load_texture(path: &str, pack: Option<&AssetPack>) -> Result<Texture2D, String> {
+        let image = AssetManager::decode_image(path, pack)?;
+        Ok(Texture2D::from_rgba8(image.width() as u16, image.height() as u16, image.as_raw()))
+    }
+
+    // The image is decoded before being given to macroquad, which panics on a broken file
+    fn decode_image(path: &str, pack: Option<&AssetPack>) -> Result<RgbaImage, String> {
+        let bytes = AssetManager::read_file(path, pack)?;
+        Ok(image::load_from_memory(&bytes).map_err(|e| format!("{} is broken: {}", path, e))?.to_rgba8())
+    }
+
+    fn read_file(path: &str, pack: Option<&AssetPack>) -> Result<Vec<u8>, String> {
+        if let Some(pack) = pack {
+            return pack.read_file(path);
+        }
+
+        fs::read(path).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("{} is missing", path),
+            _ => format!("{}: {}", path, e),
+        })
+    }
+
+    fn has_file(path: &str, pack: Option<&AssetPack>) -> bool {
+        match pack {
+            Some(pack) => pack.has_file(path),
+            None => Path::new(path).is_file(),
+        }
+    }
+
+    fn get_texture_paths(directory: &str) -> Result<Vec<String>, String> {
+        let mut png_path = Vec::new();
+        let entries = fs::read_dir(Path::new(directory)).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("{} is missing", directory),
+            _ => format!("{}: {}", directory, e),
+        })?;
+
+        for entry in entries.flatten() {
+            let file = entry.path();
+            if file.is_file() {
+                if let Some(extension) = file.extension() {
+                    if extension.to_string_lossy().to_lowercase() == "png" {
+                        png_path.push(file.as_path().to_string_lossy().to_string());
+                    }
+                }
+            }
+        }
+
+        // The directory order depends on the file system, the textures are sorted
+        // to get the same texture for the same seed on every computer
+        png_path.sort();
+
+        Ok(png_path)
+    }
+
+    async fn get_placeholder(asset_type: AssetType) -> Result<LoadedAsset, String> {
+        let placeholder_texture = || Texture2D::from_image(&Image::gen_image_color(PLACEHOLDER_TEXTURE_WIDTH,
+                                                                                  PLACEHOLDER_TEXTURE_HEIGHT,
+                                                                                  PINK));
+        match asset_type {
+            AssetType::Texture => Ok(LoadedAsset::Texture(placeholder_texture())),
+            AssetType::TextureDirectory => Ok(LoadedAsset::Textures(vec![placeholder_texture()])),
+            AssetType::Sound => {
+                let sound = load_sound_from_bytes(&AssetManager::get_silent_wav()).await
+                    .map_err(|e| format!("impossible to create a silent sound: {}", e))?;
+                Ok(LoadedAsset::Sound(sound))
+            }
+        }
+    }
+
+    // One second of silence in a 16 bits mono wav file
+    fn get_silent_wav() -> Vec<u8> {
+        let data_size = PLACEHOLDER_SOUND_SAMPLE_RATE * 2;
+
+        let mut bytes = Vec::with_capacity(44 + data_size as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        // PCM format, 1 channel
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&PLACEHOLDER_SOUND_SAMPLE_RATE.to_le_bytes());
+        // Bytes per second, bytes per frame and bits per sample
+        bytes.extend_from_slice(&(PLACEHOLDER_SOUND_SAMPLE_RATE * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        bytes.resize(44 + data_size as usize, 0);
+
+        bytes
+    }
+
+    fn get_type_name(asset_type: AssetType) -> &'static str {
+        match asset_type {
+            AssetType::Texture => "texture",
+            AssetType::TextureDirectory => "texture_directory",
+            AssetType::Sound => "sound",
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+
+use serde::Deserialize;
+use zip::ZipArchive;
+
+use crate::config::ASSET_PACK_DIRECTORY;
+use crate::game::assets::manifest::AssetEntry;
+use crate::utils::data_directory::get_data_directory;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+const PACK_DESCRIPTOR_FILE_NAME: &str = "pack.toml";
+const PACK_ARCHIVE_EXTENSION: &str = "zip";
+
+// Descriptor of a pack, at the root of its directory or of its archive
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackDescriptor {
+    name: String,
+    #[serde(default)]
+    author: Option<String>,
+    // Assets replaced by the pack, in the format of the manifest, the paths are relative to the pack
+    #[serde(default)]
+    assets: HashMap<String, AssetEntry>,
+}
+
+enum PackFiles {
+    Directory(PathBuf),
+    // The archive is small, all its files are kept in memory
+    Archive(HashMap<String, Vec<u8>>),
+}
+
+/// Theme replacing some assets of the game, from a directory or a zip archive
+pub struct AssetPack {
+    name: String,
+    author: Option<String>,
+    assets: HashMap<String, AssetEntry>,
+    files: PackFiles,
+}
+
+impl AssetPack {
+    // The pack is a path, or the name of a directory or an archive of the pack directories
+    pub fn find(name: &str) -> RustyResult<AssetPack> {
+        let path = Path::new(name);
+        if path.exists() {
+            return AssetPack::open(path);
+        }
+
+        for pack_path in AssetPack::get_pack_paths() {
+            if AssetPack::get_pack_name(&pack_path) == name {
+                return AssetPack::open(&pack_path);
+            }
+        }
+
+        Err(RustyError::Asset(format!("the asset pack {} does not exist", name)))
+    }
+
+    pub fn open(path: &Path) -> RustyResult<AssetPack> {
+        let invalid_pack = |reason: String| RustyError::Asset(
+            format!("{} is not a valid asset pack: {}", path.display(), reason)
+        );
+
+        let files = if path.is_dir() {
+            PackFiles::Directory(path.to_path_buf())
+        } else {
+            PackFiles::Archive(AssetPack::read_archive(path).map_err(invalid_pack)?)
+        };
+
+        let descriptor_bytes = AssetPack::read_pack_file(&files, PACK_DESCRIPTOR_FILE_NAME).map_err(invalid_pack)?;
+        let descriptor: PackDescriptor = toml::from_str(&String::from_utf8_lossy(&descriptor_bytes))
+            .map_err(|e| invalid_pack(format!("{}: {}", PACK_DESCRIPTOR_FILE_NAME, e)))?;
+
+        // A pack only reads its own files
+        for (name, entry) in &descriptor.assets {
+            for path in std::iter::once(&entry.path).chain(&entry.fallback) {
+                AssetPack::check_path(path).map_err(|e| invalid_pack(format!("{}: {}: {}", PACK_DESCRIPTOR_FILE_NAME, name, e)))?;
+            }
+        }
+
+        Ok(AssetPack {
+            name: descriptor.name,
+            author: descriptor.author,
+            assets: descriptor.assets,
+            files,
+        })
+    }
+
+    // Names of the packs of the pack directories, sorted
+    pub fn get_available_packs() -> Vec<String> {
+        let mut names: Vec<String> = AssetPack::get_pack_paths().iter().map(|path| AssetPack::get_pack_name(path)).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn get_assets(&self) -> &HashMap<String, AssetEntry> {
+        &self.assets
+    }
+
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>, String> {
+        AssetPack::read_pack_file(&self.files, path)
+    }
+
+    pub fn has_file(&self, path: &str) -> bool {
+        match &self.files {
+            PackFiles::Directory(root) => AssetPack::get_file_path(root, path).is_ok_and(|path| path.is_file()),
+            PackFiles::Archive(files) => files.contains_key(path),
+        }
+    }
+
+    // Paths of the png files of a directory of the pack
+    pub fn get_texture_paths(&self, directory: &str) -> Result<Vec<String>, String> {
+        let directory = directory.trim_end_matches('/');
+        let mut png_path = Vec::new();
+
+        match &self.files {
+            PackFiles::Directory(root) => {
+                let entries = fs::read_dir(AssetPack::get_file_path(root, directory)?).map_err(|e| match e.kind() {
+                    ErrorKind::NotFound => format!("{} is missing in the pack", directory),
+                    _ => format!("{}: {}", directory, e),
+                })?;
+
+                for entry in entries.flatten() {
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+                    if entry.path().is_file() && AssetPack::is_png(&file_name) {
+                        png_path.push(format!("{}/{}", directory, file_name));
+                    }
+                }
+            }
+            PackFiles::Archive(files) => {
+                let prefix = format!("{}/", directory);
+                for file_path in files.keys() {
+                    if let Some(file_name) = file_path.strip_prefix(&prefix) {
+                        if !file_name.contains('/') && AssetPack::is_png(file_name) {
+                            png_path.push(file_path.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        // Same order on every computer, for the same texture with the same seed
+        png_path.sort();
+
+        Ok(png_path)
+    }
+
+    fn read_pack_file(files: &PackFiles, path: &str) -> Result<Vec<u8>, String> {
+        match files {
+            PackFiles::Directory(root) => fs::read(AssetPack::get_file_path(root, path)?).map_err(|e| match e.kind() {
+                ErrorKind::NotFound => format!("{} is missing in the pack", path),
+                _ => format!("{}: {}", path, e),
+            }),
+            PackFiles::Archive(files) => files.get(path).cloned()
+                .ok_or_else(|| format!("{} is missing in the pack", path)),
+        }
+    }
+
+    // Path of a file of a pack directory, the paths going out of the pack are refused
+    fn get_file_path(root: &Path, path: &str) -> Result<PathBuf, String> {
+        AssetPack::check_path(path)?;
+        Ok(root.join(path))
+    }
+
+    // The paths of a pack are relative to the pack and stay in it: no absolute path and no `..`
+    fn check_path(path: &str) -> Result<(), String> {
+        let is_outside = Path::new(path).components().any(|component| matches!(component, Component::ParentDir | Component::RootDir | Component::Prefix(_)));
+        if is_outside {
+            return Err(format!("{} is not a path in the pack", path));
+        }
+        Ok(())
+    }
+
+    fn read_archive(path: &Path) -> Result<HashMap<String, Vec<u8>>, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+
+        let mut files = HashMap::new();
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).map_err(|e| e.to_string())?;
+            if file.is_file() {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content).map_err(|e| format!("{}: {}", file.name(), e))?;
+                files.insert(file.name().to_string(), content);
+            }
+        }
+
+        Ok(files)
+    }
+
+    // The packs are searched next to the game, then in the user data directory
+    pub fn get_pack_directories() -> Vec<PathBuf> {
+        vec![PathBuf::from(ASSET_PACK_DIRECTORY), get_data_directory().join(ASSET_PACK_DIRECTORY)]
+    }
+
+    fn get_pack_paths() -> Vec<PathBuf> {
+        let mut pack_paths = Vec::new();
+
+        for directory in AssetPack::get_pack_directories() {
+            if let Ok(entries) = fs::read_dir(&directory) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let is_archive = path.extension().is_some_and(|extension| extension == PACK_ARCHIVE_EXTENSION);
+                    if path.join(PACK_DESCRIPTOR_FILE_NAME).is_file() || (path.is_file() && is_archive) {
+                        pack_paths.push(path);
+                    }
+                }
+            }
+        }
+
+        pack_paths
+    }
+
+    // A pack is selected by the name of its directory or of its archive without extension
+    fn get_pack_name(path: &Path) -> String {
+        path.file_stem().map_or_else(String::new, |name| name.to_string_lossy().to_string())
+    }
+
+    fn is_png(file_name: &str) -> bool {
+        file_name.to_lowercase().ends_with(".png")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn paths_out_of_the_pack_are_refused() {
+        assert!(AssetPack::check_path("textures/road.png").is_ok());
+        assert!(AssetPack::check_path("./textures/road.png").is_ok());
+        assert!(AssetPack::check_path("../textures/road.png").is_err());
+        assert!(AssetPack::check_path("textures/../../road.png").is_err());
+        assert!(AssetPack::check_path("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn pack_reading_out_of_its_directory_is_invalid() {
+        let directory = env::temp_dir().join("rusty_corks_pack_test");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join(PACK_DESCRIPTOR_FILE_NAME), "\
+name = \"Test\"
+
+[assets.road]
+type = \"texture\"
+path = \"road.png\"
+fallback = \"../road.png\"
+").unwrap();
+
+        let result = AssetPack::open(&directory);
+        let read_result = AssetPack::read_pack_file(&PackFiles::Directory(directory.clone()), "../pack.toml");
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(result.is_err_and(|e| e.to_string().contains("../road.png is not a path in the pack")));
+        assert!(read_result.is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use log::info;
+use serde::Deserialize;
+
+use crate::config::ASSET_MANIFEST_PATH;
+use crate::config::config_file::Config;
+use crate::game::assets::asset_pack::AssetPack;
+use crate::game::assets::asset_manager::GAME_MUSIC;
+use crate::game::rhythm::level::Level;
+use crate::game::rhythm::Tempo;
+use crate::game::sounds::sounds_manager::SoundsManager;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetType {
+    Texture,
+    TextureDirectory,
+    Sound,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetEntry {
+    #[serde(rename = "type")]
+    pub asset_type: AssetType,
+    pub path: String,
+    // A missing optional asset is replaced by a placeholder instead of stopping the game
+    #[serde(default)]
+    pub optional: bool,
+    // Tried when the file of the path is missing or broken
+    #[serde(default)]
+    pub fallback: Option<String>,
+    // Hitbox [x, y, width, height] of a texture by file name, the opaque pixels of the texture without it
+    #[serde(default)]
+    pub hitboxes: HashMap<String, [u32; 4]>,
+    // Tempo of a music by file name, used by the rhythm mode
+    #[serde(default)]
+    pub tempos: HashMap<String, Tempo>,
+    // The paths are in the asset pack instead of the game directory
+    #[serde(skip)]
+    pub in_pack: bool,
+}
+
+/// List of the assets of the game by name, read from the manifest file
+pub struct AssetManifest {
+    entries: HashMap<String, AssetEntry>,
+}
+
+impl AssetManifest {
+    pub fn load(path: &Path) -> RustyResult<AssetManifest> {
+        let content = fs::read_to_string(path).map_err(RustyError::file("read the asset manifest", path))?;
+
+        let entries: HashMap<String, AssetEntry> = toml::from_str(&content).map_err(|e| RustyError::Asset(
+            format!("{}: {}", path.display(), e)
+        ))?;
+
+        Ok(AssetManifest {
+            entries,
+        })
+    }
+
+    // Manifest of the game with the changes of the asset pack, of the configuration and of the level,
+    // the pack is returned to read its files
+    pub fn load_for_game(config: &Config, asset_pack: Option<&str>, level: Option<&Level>) -> RustyResult<(AssetManifest, Option<AssetPack>)> {
+        let mut manifest = AssetManifest::load(Path::new(ASSET_MANIFEST_PATH))?;
+
+        let pack = match asset_pack {
+            Some(name) => {
+                let pack = AssetPack::find(name)?;
+                manifest.apply_pack(&pack)?;
+                match pack.get_author() {
+                    Some(author) => info!("Asset pack {} by {}", pack.get_name(), author),
+                    None => info!("Asset pack {}", pack.get_name()),
+                }
+                Some(pack)
+            }
+            None => None,
+        };
+
+        // The musics chosen in the configuration replace the ones of the manifest and of the pack
+        for (sound_type, sound_config) in config.sounds.get_sounds() {
+            if let Some(path) = &sound_config.path {
+                manifest.set_path(SoundsManager::get_asset_name(sound_type), path);
+            }
+            if let Some(tempo) = sound_config.tempo {
+                manifest.set_tempo(SoundsManager::get_asset_name(sound_type), tempo);
+            }
+        }
+
+        // A level is played on its own music and tempo
+        if let Some(level) = level {
+            if let Some(music) = &level.music {
+                manifest.set_path(GAME_MUSIC, music);
+            }
+            manifest.set_tempo(GAME_MUSIC, level.get_tempo());
+        }
+
+        Ok((manifest, pack))
+    }
+
+    pub fn get_entry(&self, name: &str) -> Option<&AssetEntry> {
+        self.entries.get(name)
+    }
+
+    // The path of an entry is replaced, used for the musics chosen in the configuration
+    pub fn set_path(&mut self, name: &str, path: &str) {
+        if let Some(entry) = self.entries.get_mut(name) {
+            entry.path = path.to_string();
+            entry.in_pack = false;
+        }
+    }
+
+    // The tempo of the configuration is used whatever the file of the entry which is played
+    pub fn set_tempo(&mut self, name: &str, tempo: Tempo) {
+        if let Some(entry) = self.entries.get_mut(name) {
+            let paths = [Some(entry.path.clone()), entry.fallback.clone()];
+            for path in paths.into_iter().flatten() {
+                entry.tempos.insert(get_file_name(&path), tempo);
+            }
+        }
+    }
+
+    // The assets of the pack replace the ones of the game, the others are kept
+    pub fn apply_pack(&mut self, pack: &AssetPack) -> RustyResult<()> {
+        for (name, pack_entry) in pack.get_assets() {
+            if !self.entries.contains_key(name) {
+                return Err(RustyError::Asset(
+                    format!("the asset pack {} replaces {}, which is not an asset of the game", pack.get_name(), name)
+                ));
+            }
+
+            let mut entry = pack_entry.clone();
+            entry.in_pack = true;
+            self.entries.insert(name.clone(), entry);
+        }
+
+        Ok(())
+    }
+}
+
+// The hitboxes and the tempos of an entry are given by file name
+pub fn get_file_name(path: &str) -> String {
+    Path::new(path).file_name().map_or_else(|| path.to_string(), |file_name| file_name.to_string_lossy().to_string())
+}
@@ -0,0 +1,3 @@
+pub mod asset_manager;
+pub mod asset_pack;
+pub mod manifest;
@@ -0,0 +1,177 @@
+use std::time::Instant;
+
+use log::warn;
+use rand::Rng;
+
+use crate::config::SIMULATION_STEP_DURATION;
+use crate::config::config_file::{Config, GameConfig};
+use crate::game::assets::asset_manager::{AssetManager, GAME_MUSIC};
+use crate::game::assets::manifest::AssetManifest;
+use crate::game::car::{Car, PLAYER_CAR_X_POSITION, Way};
+use crate::game::car::hitbox::CarHitboxes;
+use crate::game::game::GameOptions;
+use crate::game::replay::{Replay, ReplayPlayer};
+use crate::game::rhythm::BeatTrack;
+use crate::game::rhythm::level::LevelTrack;
+use crate::game::simulation::{GameState, Simulation};
+use crate::keyboard::GameAction;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+// A run of the automatic driver is stopped after this simulated time, in seconds
+const MAX_RUN_DURATION: f32 = 600.0;
+// The automatic driver changes lane when the next car of its lane is reached within this time, in seconds
+const AUTOPILOT_LOOKAHEAD_TIME: f32 = 0.6;
+// Space kept around the other cars, in pixels
+const AUTOPILOT_SAFETY_MARGIN: f32 = 30.0;
+
+// Plays the runs without window, as fast as possible, and prints the results.
+// The traffic of a seed is the same as in the window, the hitboxes of the cars and the beats of the music
+// are read from the assets
+pub fn run_benchmark(config: &Config, options: GameOptions, runs: u32) -> RustyResult<()> {
+    let (manifest, pack) = AssetManifest::load_for_game(config, options.asset_pack.as_deref(), options.level.as_ref())?;
+    let hitboxes = CarHitboxes::load(&manifest, pack.as_ref())?;
+    let beat_track = AssetManager::load_beat_track(&manifest, pack.as_ref(), GAME_MUSIC)?;
+    let game_config = options.get_game_config(&config.game);
+    let level = options.level.as_ref().map(LevelTrack::from_level);
+
+    match options.replay {
+        Some(replay) => check_replay(&game_config, hitboxes, beat_track, level, replay),
+        None => {
+            let first_seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+            run_autopilot(&game_config, &hitboxes, &beat_track, level.as_ref(), first_seed, runs)
+        }
+    }
+}
+
+// The replay is played and its score compared with the recorded one, a different score is an error
+fn check_replay(game_config: &GameConfig, hitboxes: CarHitboxes, beat_track: BeatTrack, level: Option<LevelTrack>, replay: Replay) -> RustyResult<()> {
+    let mut simulation = Simulation::new(game_config, hitboxes, beat_track, level, Some(replay.seed));
+    if replay.config_hash != simulation.get_config_hash() {
+        warn!("The replay was recorded with other game settings, the run may be different");
+    }
+
+    let mut replay_player = ReplayPlayer::new(replay);
+    let start_time = Instant::now();
+
+    // The run of the replay starts immediately, then the actions come from the replay.
+    // A run quit from the pause ends in the pause
+    simulation.step(SIMULATION_STEP_DURATION, GameAction::PauseResume)?;
+    while simulation.get_state() != GameState::GameOver && simulation.get_game_time() < MAX_RUN_DURATION
+        && !(simulation.get_state() == GameState::Pause && replay_player.is_finished()) {
+        let action = replay_player.get_action(simulation.get_run_step());
+        simulation.step(SIMULATION_STEP_DURATION, action)?;
+    }
+
+    let score = simulation.get_score();
+    println!("Replay played in {:.3} s: score {}, {} steps",
+             start_time.elapsed().as_secs_f64(),
+             score,
+             simulation.get_run_step());
+
+    match replay_player.get_replay().final_score {
+        Some(final_score) if final_score != score => Err(RustyError::Replay(
+            format!("the replay finished with a score of {} instead of {}", score, final_score)
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Each run has its own seed, following the first one, so a slow run can be replayed with --seed
+fn run_autopilot(game_config: &GameConfig, hitboxes: &CarHitboxes, beat_track: &BeatTrack, level: Option<&LevelTrack>, first_seed: u64, runs: u32) -> RustyResult<()> {
+    let start_time = Instant::now();
+    let mut total_steps: u64 = 0;
+    let mut total_score: u64 = 0;
+    let mut simulated_time: f64 = 0.0;
+
+    for run in 0..runs {
+        let seed = first_seed.wrapping_add(run as u64);
+        let mut simulation = Simulation::new(game_config, hitboxes.clone(), beat_track.clone(), level.cloned(), Some(seed));
+
+        simulation.step(SIMULATION_STEP_DURATION, GameAction::PauseResume)?;
+        while simulation.get_state() != GameState::GameOver && simulation.get_game_time() < MAX_RUN_DURATION {
+            let action = get_autopilot_action(&simulation);
+            simulation.step(SIMULATION_STEP_DURATION, action)?;
+        }
+
+        println!("Run {}: seed {}, score {}, {:.1} s, {} steps{}",
+                 run + 1,
+                 seed,
+                 simulation.get_score(),
+                 simulation.get_game_time(),
+                 simulation.get_run_step(),
+                 if simulation.is_level_complete() { ", level complete" } else { "" });
+
+        total_steps += simulation.get_run_step() as u64;
+        total_score += simulation.get_score() as u64;
+        simulated_time += simulation.get_game_time() as f64;
+    }
+
+    let elapsed_time = start_time.elapsed().as_secs_f64().max(f64::EPSILON);
+    println!("{} runs, {} steps in {:.3} s: {:.0} steps per second, {:.0} times faster than real time, average score {}",
+             runs,
+             total_steps,
+             elapsed_time,
+             total_steps as f64 / elapsed_time,
+             simulated_time / elapsed_time,
+             total_score / runs.max(1) as u64);
+
+    Ok(())
+}
+
+// Goes towards the lane whose next car is the farthest, without crossing a car on the way
+fn get_autopilot_action(simulation: &Simulation) -> GameAction {
+    // Distance travelled by the traffic from `from_distance` until the next car of the lane reaches the player car,
+    // the cars which have passed the player car at `from_distance` are ignored
+    let hitboxes = simulation.get_hitboxes();
+    let (player_car_x, _, player_car_width, _) = hitboxes.player_car.get_rectangle();
+    let player_car_back = PLAYER_CAR_X_POSITION + player_car_x;
+    let player_car_front = player_car_back + player_car_width;
+    let get_free_distance = |way: Way, from_distance: f32| simulation.get_bot_cars().iter()
+        .filter(|bot_car| bot_car.get_way() == way)
+        .map(|bot_car| {
+            let (bot_car_x, _, bot_car_width, _) = hitboxes.get_bot_car(bot_car.get_texture_id()).get_rectangle();
+            (bot_car.x_position + bot_car_x, bot_car.x_position + bot_car_x + bot_car_width)
+        })
+        .filter(|&(_, bot_car_front)| bot_car_front + AUTOPILOT_SAFETY_MARGIN - player_car_back > from_distance)
+        .map(|(bot_car_back, _)| bot_car_back - (player_car_front + AUTOPILOT_SAFETY_MARGIN) - from_distance)
+        .fold(f32::MAX, f32::min);
+
+    // The car does nothing before the end of a lane change
+    let player_car = simulation.get_player_car();
+    if player_car.is_changing_lane() {
+        return GameAction::None;
+    }
+
+    let player_way = player_car.get_way();
+    let lookahead = simulation.get_speed() * AUTOPILOT_LOOKAHEAD_TIME;
+    // Distance travelled by the traffic while the player car crosses a lane
+    let crossing_distance = simulation.get_speed() * simulation.get_lane_change_duration();
+    let mut best_action = GameAction::None;
+    let mut best_distance = get_free_distance(player_way, 0.0);
+
+    // The car only changes lane when the next car is close, a far lane is reached through the lanes between them
+    if best_distance > lookahead {
+        return GameAction::None;
+    }
+
+    for action in [GameAction::Up, GameAction::Down] {
+        let mut way = player_way;
+        // The car overlaps a lane from the middle of the lane change towards it
+        let mut arrival_distance = crossing_distance / 2.0;
+        while let Some(next_way) = simulation.get_destination_way(action, way) {
+            // A car reaching the player car before it has left the lane blocks the way
+            if get_free_distance(next_way, arrival_distance) <= crossing_distance {
+                break;
+            }
+            way = next_way;
+
+            if get_free_distance(way, arrival_distance) > best_distance {
+                best_distance = get_free_distance(way, arrival_distance);
+                best_action = action;
+            }
+            arrival_distance += crossing_distance;
+        }
+    }
+
+    best_action
+}
@@ -1,96 +1,89 @@
-use std::path::Path;
-
-use macroquad::prelude::{load_texture, screen_width, Texture2D};
-use rand::Rng;
-
-use crate::game::car::{BOT_CAR_WIDTH, Car, PLAYER_CAR_WIDTH, PLAYER_CAR_X_POSITION, Way};
+use crate::WINDOW_WIDTH;
+use crate::game::car::{Car, PLAYER_CAR_X_POSITION, Way};
+use crate::game::car::hitbox::CarHitboxes;
 use crate::game::car::player_car::PlayerCar;
-use crate::utils::rusty_error::RustyResult;
-
-const BOT_CAR_TEXTURE_PATH: &str = "assets/cars/bots/";
 
 #[derive(Clone)]
 pub struct BotCar {
-    texture: Texture2D,
+    // Index of the texture in the bot car textures loaded by the graphics manager
+    texture_id: usize,
     way: Way,
     pub x_position: f32,
+    // Position before the last update, used to interpolate the drawing between two simulation steps
+    previous_x_position: f32,
     speed: f32,
 }
 
 impl BotCar {
-    pub async fn new(way: Way) -> RustyResult<BotCar> {
-        let mut png_path = Vec::new();
-        let directory = Path::new(BOT_CAR_TEXTURE_PATH);
-
-        // It is a complex code but we don't have the choice
-        if directory.is_dir() {
-            for entry in (std::fs::read_dir(directory)?).flatten() {
-                let file = entry.path();
-                if file.is_file() {
-                    if let Some(extension) = file.extension() {
-                        if extension.to_string_lossy().to_lowercase() == "png" {
-                            png_path.push(file.as_path().to_string_lossy().to_string());
-                        }
-                    }
-                }
-            }
-        }
-
-        let mut rng = rand::thread_rng();
-        let texture_rng = png_path.get(rng.gen_range(0..png_path.len()));
-
-        if let Some(texture_rng) = texture_rng {
-            let car_texture = load_texture(texture_rng).await?;
-            Ok(BotCar {
-                texture: car_texture,
-                way,
-                speed: 0.0,
-                x_position: screen_width(),
-            })
-        } else {
-            unreachable!();
+    pub fn new(way: Way, texture_id: usize) -> BotCar {
+        BotCar {
+            texture_id,
+            way,
+            speed: 0.0,
+            x_position: WINDOW_WIDTH,
+            previous_x_position: WINDOW_WIDTH,
         }
     }
 
     pub fn update_position(&mut self, delta_time: f32) {
+        self.previous_x_position = self.x_position;
         self.x_position -= self.speed * delta_time;
     }
 
-    pub fn is_out_of_screen(&self) -> bool {
-        self.x_position < -screen_width() - BOT_CAR_WIDTH
+    // Position between the previous and the current step, `interpolation` goes from 0.0 to 1.0
+    pub fn get_interpolated_x_position(&self, interpolation: f32) -> f32 {
+        self.previous_x_position + (self.x_position - self.previous_x_position) * interpolation
+    }
+
+    pub fn is_out_of_screen(&self, hitboxes: &CarHitboxes) -> bool {
+        let (width, _) = hitboxes.get_bot_car(self.texture_id).get_texture_size();
+        self.x_position < -WINDOW_WIDTH - width
     }
-    pub fn is_colliding(&self, player_car: &PlayerCar) -> Option<(Way, f32)> {
-        let mut ret = None;
 
-        // Check if the player car is on the same lane as the bot car.
-        if player_car.get_way() == self.way {
-            let bot_car_back_x = self.x_position;
-            let bot_car_front_x = self.x_position + PLAYER_CAR_WIDTH;
-            let player_car_back_x = PLAYER_CAR_X_POSITION;
-            let player_car_front_x = PLAYER_CAR_X_POSITION + PLAYER_CAR_WIDTH;
+    // Top of the texture of the car, centered on its lane
+    pub fn get_y_position(&self, lane_positions: &[f32], hitboxes: &CarHitboxes) -> f32 {
+        let (_, height) = hitboxes.get_bot_car(self.texture_id).get_texture_size();
+        lane_positions[self.way.0] - height / 2.0
+    }
 
-            // Check for intersection in the X-axis.
-            if !(bot_car_front_x <= player_car_back_x || player_car_front_x <= bot_car_back_x) {
-                // Calculate the midpoint of the overlap as the point of collision.
-                let overlap_back = bot_car_back_x.max(player_car_back_x);
-                let overlap_front = bot_car_front_x.min(player_car_front_x);
+    // Puts the car back where it was at `progress` (0 to 1) of the last update, at the moment of a collision
+    pub fn rewind_position(&mut self, progress: f32) {
+        self.x_position = self.get_interpolated_x_position(progress);
+    }
 
-                ret = Some((self.way, overlap_back + (overlap_front - overlap_back) / 2.0))
-            }
-        }
-        ret
+    // First contact with the player car during the last update, as the progress of the update (0 to 1)
+    // and the point of the collision. Both cars are followed from their previous position, a fast car
+    // cannot go through the player car between two updates, and during a lane change
+    // the player car can hit the cars of two lanes
+    pub fn is_colliding(&self, player_car: &PlayerCar, lane_positions: &[f32], hitboxes: &CarHitboxes, pixel_collision: bool) -> Option<(f32, (f32, f32))> {
+        // The cars are drawn from the top left corner of their texture
+        let bot_car_y = self.get_y_position(lane_positions, hitboxes);
+        let (_, player_car_height) = hitboxes.player_car.get_texture_size();
+        let player_car_start = (PLAYER_CAR_X_POSITION, player_car.get_previous_y_position() - player_car_height / 2.0);
+        let player_car_end = (PLAYER_CAR_X_POSITION, player_car.get_y_position() - player_car_height / 2.0);
+
+        hitboxes.get_bot_car(self.texture_id).get_swept_collision((self.previous_x_position, bot_car_y),
+                                                                  (self.x_position, bot_car_y),
+                                                                  &hitboxes.player_car,
+                                                                  player_car_start,
+                                                                  player_car_end,
+                                                                  pixel_collision)
     }
 
     pub fn set_speed(&mut self, speed: f32) {
         self.speed = speed;
     }
-}
 
-impl Car for BotCar {
-    fn get_texture(&self) -> Texture2D {
-        self.texture
+    pub fn get_texture_id(&self) -> usize {
+        self.texture_id
     }
 
+    pub fn set_texture_id(&mut self, texture_id: usize) {
+        self.texture_id = texture_id;
+    }
+}
+
+impl Car for BotCar {
     fn get_way(&self) -> Way {
         self.way
     }
@@ -1,100 +1,126 @@
-use macroquad::prelude::screen_width;
 use rand::Rng;
+use rand_chacha::ChaCha8Rng;
 
-use crate::config::DISTANCE_BETWEEN_CARS;
+use crate::WINDOW_WIDTH;
 use crate::game::car::bot_car::BotCar;
 use crate::game::car::Car;
 use crate::game::car::Way;
-use crate::utils::rusty_error::RustyError::LaneNotFound;
-use crate::utils::rusty_error::RustyResult;
+use crate::game::rhythm::level::PlannedCar;
+use crate::utils::rusty_error::{RustyError, RustyResult};
 
 pub struct BotManager {
     pub bot_car_list: Vec<BotCar>,
-    lanes: [Lane; 3],
-}
-
-#[derive(PartialEq)]
-struct Lane {
-    way: Way,
+    lane_count: usize,
+    // Number of textures available to draw the bot cars
+    bot_texture_count: usize,
+    // More the value is high, more the distance is
+    distance_between_cars: f32,
 }
 
 impl BotManager {
-    pub fn new() -> BotManager {
+    pub fn new(lane_count: usize, bot_texture_count: usize, distance_between_cars: f32) -> BotManager {
         BotManager {
             bot_car_list: Vec::new(),
-            lanes: [
-                Lane {
-                    way: Way::Upper,
-                },
-                Lane {
-                    way: Way::Center,
-                },
-                Lane {
-                    way: Way::Lower,
-                },
-            ],
+            lane_count,
+            bot_texture_count,
+            distance_between_cars,
         }
     }
-    pub async fn spawn_car(&mut self) -> RustyResult<()> {
-        let mut rng = rand::thread_rng();
-        let way_idx = rng.gen_range(0..self.lanes.len());
-        let way = match way_idx {
-            0 => Way::Upper,
-            1 => Way::Center,
-            _ => Way::Lower,
-        };
-        let lane_pos = self.lanes.iter().position(|l| l.way == way).ok_or(LaneNotFound)?;
-        let lane = &self.lanes[lane_pos];
-        if self.is_lane_free(lane)? {
+    pub fn spawn_car(&mut self, rng: &mut ChaCha8Rng) -> RustyResult<()> {
+        if self.bot_texture_count == 0 {
+            return Err(RustyError::Asset("no texture to draw the bot cars".to_string()));
+        }
+
+        let way = Way(rng.gen_range(0..self.lane_count));
+        if self.is_lane_free(way) {
             // Create a new car and add it to the lane
-            let bot_car = BotCar::new(lane.way).await?;
+            let bot_car = BotCar::new(way, rng.gen_range(0..self.bot_texture_count));
             self.bot_car_list.push(bot_car);
         }
         Ok(())
     }
-    fn is_lane_recently_used(&self, lane: &Lane) -> RustyResult<bool> {
+
+    // The car of a level enters the road even if its lane is not free, the level decides the traffic.
+    // A car without texture gets a random one. The level is checked with the road and the textures when it is loaded
+    pub fn spawn_level_car(&mut self, planned_car: &PlannedCar, rng: &mut ChaCha8Rng) -> RustyResult<()> {
+        if self.bot_texture_count == 0 {
+            return Err(RustyError::Asset("no texture to draw the bot cars".to_string()));
+        }
+
+        let texture_id = match planned_car.texture_id {
+            Some(texture_id) => texture_id,
+            None => rng.gen_range(0..self.bot_texture_count),
+        };
+        if planned_car.way.0 >= self.lane_count || texture_id >= self.bot_texture_count {
+            return Err(RustyError::Level(format!("the car {} of the lane {} is not in the game", texture_id + 1, planned_car.way.0 + 1)));
+        }
+
+        self.bot_car_list.push(BotCar::new(planned_car.way, texture_id));
+        Ok(())
+    }
+
+    // Used when the assets are reloaded, the cars on the road keep their texture index,
+    // the last texture when their texture is gone
+    pub fn set_bot_texture_count(&mut self, bot_texture_count: usize) {
+        self.bot_texture_count = bot_texture_count;
+        for bot_car in self.bot_car_list.iter_mut() {
+            bot_car.set_texture_id(bot_car.get_texture_id().min(bot_texture_count.saturating_sub(1)));
+        }
+    }
+
+    // The cars of the previous lanes are removed, they could be out of the road
+    pub fn set_lane_count(&mut self, lane_count: usize) {
+        self.lane_count = lane_count;
+        self.bot_car_list.clear();
+    }
+
+    pub fn set_distance_between_cars(&mut self, distance_between_cars: f32) {
+        self.distance_between_cars = distance_between_cars;
+    }
+
+    pub fn get_bot_texture_count(&self) -> usize {
+        self.bot_texture_count
+    }
+
+    fn is_lane_recently_used(&self, way: Way) -> bool {
         // Verified if the lane has been recently used by checking
         // The time elapsed since the last appearance of an object in the lane.
 
         let mut recently_used = false;
 
         for bot_car in &self.bot_car_list {
-            if (screen_width() - bot_car.x_position) < (screen_width() / (1.0 / DISTANCE_BETWEEN_CARS)) && bot_car.get_way() == lane.way {
+            if (WINDOW_WIDTH - bot_car.x_position) < (WINDOW_WIDTH / (1.0 / self.distance_between_cars)) && bot_car.get_way() == way {
                 recently_used = true;
                 break;
             }
         }
-        Ok(recently_used)
+        recently_used
     }
-    fn is_lane_free(&self, lane: &Lane) -> RustyResult<bool> {
+    fn is_lane_free(&self, way: Way) -> bool {
         let is_free;
 
         // Check if the list of bot cars is empty, which means the lane is free.
         if self.bot_car_list.is_empty() {
             is_free = true;
             // If the lane was recently used, mark it as not free.
-        } else if self.is_lane_recently_used(lane)? {
+        } else if self.is_lane_recently_used(way) {
             is_free = false;
             // If the lane has not been recently used, check adjacent lanes.
         } else {
-            let lane_position = match self.lanes.iter().position(|l| l == lane) {
-                Some(i) => i,
-                // Unreachable should never be reached.
-                None => unreachable!(),
-            };
+            let Way(lane_position) = way;
 
             if lane_position == 0 {
                 // If it is the first lane, check the next lane's recent usage.
-                is_free = !self.is_lane_recently_used(&self.lanes[lane_position + 1])?
-            } else if lane_position == self.lanes.len() - 1 {
+                is_free = !self.is_lane_recently_used(Way(lane_position + 1))
+            } else if lane_position == self.lane_count - 1 {
                 // If it is the last lane, check the previous lane's recent usage.
-                is_free = !self.is_lane_recently_used(&self.lanes[lane_position - 1])?
+                is_free = !self.is_lane_recently_used(Way(lane_position - 1))
             } else {
                 // If it is an intermediate lane, check both adjacent lanes.
-                is_free = !(self.is_lane_recently_used(&self.lanes[lane_position - 1])? &&
-                    self.is_lane_recently_used(&self.lanes[lane_position + 1])?)
+                is_free = !(self.is_lane_recently_used(Way(lane_position - 1)) &&
+                    self.is_lane_recently_used(Way(lane_position + 1)))
             }
         }
-        Ok(is_free)
+        is_free
     }
 }
\ No newline at end of file
@@ -0,0 +1,377 @@
+use std::hash::Hasher;
+
+use image::RgbaImage;
+
+use crate::game::assets::asset_manager::{AssetManager, BOT_CAR_TEXTURES, PLAYER_CAR_TEXTURE};
+use crate::game::assets::asset_pack::AssetPack;
+use crate::game::assets::manifest::AssetManifest;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+use crate::utils::stable_hasher::StableHasher;
+
+// A pixel with a lower alpha is transparent, it is not a part of the car
+const OPAQUE_ALPHA: u8 = 128;
+
+/// Part of a car texture which collides, found from the opaque pixels of the texture
+/// or declared in the asset manifest
+#[derive(Clone, PartialEq)]
+pub struct Hitbox {
+    // Rectangle of the hitbox in pixels, from the top left corner of the texture
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    // Opaque pixels of the rectangle, row by row, used by the pixel collision
+    mask: Vec<bool>,
+    // Size of the whole texture, a car is drawn centered on its lane
+    texture_width: u32,
+    texture_height: u32,
+}
+
+/// Hitboxes of the player car and of the bot cars, in the order of the bot car textures
+#[derive(Clone, PartialEq)]
+pub struct CarHitboxes {
+    pub player_car: Hitbox,
+    pub bot_cars: Vec<Hitbox>,
+}
+
+impl Hitbox {
+    // The hitbox is the rectangle around the opaque pixels, unless a rectangle (x, y, width, height) is declared.
+    // A texture without opaque pixel has an empty hitbox, it never collides
+    pub fn from_image(image: &RgbaImage, declared_rectangle: Option<[u32; 4]>) -> Hitbox {
+        let is_opaque = |x: u32, y: u32| image.get_pixel(x, y)[3] >= OPAQUE_ALPHA;
+
+        let [x, y, width, height] = match declared_rectangle {
+            Some([x, y, width, height]) => {
+                // The texture is drawn anyway, the part of the rectangle out of the texture is ignored
+                [
+                    x.min(image.width()),
+                    y.min(image.height()),
+                    width.min(image.width().saturating_sub(x)),
+                    height.min(image.height().saturating_sub(y)),
+                ]
+            }
+            None => {
+                let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+                for (x, y, _) in image.enumerate_pixels().filter(|&(x, y, _)| is_opaque(x, y)) {
+                    left = left.min(x);
+                    top = top.min(y);
+                    right = right.max(x + 1);
+                    bottom = bottom.max(y + 1);
+                }
+                if right == 0 {
+                    [0, 0, 0, 0]
+                } else {
+                    [left, top, right - left, bottom - top]
+                }
+            }
+        };
+
+        let mut mask = Vec::with_capacity((width * height) as usize);
+        for mask_y in y..y + height {
+            for mask_x in x..x + width {
+                mask.push(is_opaque(mask_x, mask_y));
+            }
+        }
+
+        Hitbox {
+            x,
+            y,
+            width,
+            height,
+            mask,
+            texture_width: image.width(),
+            texture_height: image.height(),
+        }
+    }
+
+    // Hitbox of a whole texture, for the placeholders
+    pub fn from_size(width: u32, height: u32) -> Hitbox {
+        Hitbox {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            mask: vec![true; (width * height) as usize],
+            texture_width: width,
+            texture_height: height,
+        }
+    }
+
+    // Rectangle (x, y, width, height) from the top left corner of the texture
+    pub fn get_rectangle(&self) -> (f32, f32, f32, f32) {
+        (self.x as f32, self.y as f32, self.width as f32, self.height as f32)
+    }
+
+    // Size (width, height) of the texture of the hitbox
+    pub fn get_texture_size(&self) -> (f32, f32) {
+        (self.texture_width as f32, self.texture_height as f32)
+    }
+
+    // The coordinates are in pixels from the top left corner of the rectangle
+    pub fn is_opaque(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height && self.mask[(y * self.width + x) as usize]
+    }
+
+    // Point of the collision of two cars, `position` and `other_position` are the top left corners of their textures.
+    // Without the pixel collision the rectangles are enough, with it an opaque pixel of each car must overlap
+    pub fn get_collision(&self, position: (f32, f32), other: &Hitbox, other_position: (f32, f32), pixel_collision: bool) -> Option<(f32, f32)> {
+        let (left, top, right, bottom) = self.get_overlap(position, other, other_position);
+        if left >= right || top >= bottom {
+            return None;
+        }
+
+        if !pixel_collision {
+            return Some(((left + right) / 2.0, (top + bottom) / 2.0));
+        }
+
+        // Center of the screen pixels covered by an opaque pixel of both cars
+        let mut overlap_count = 0;
+        let (mut overlap_x, mut overlap_y) = (0.0, 0.0);
+        for screen_y in top.floor() as i32..bottom.ceil() as i32 {
+            for screen_x in left.floor() as i32..right.ceil() as i32 {
+                let center = (screen_x as f32 + 0.5, screen_y as f32 + 0.5);
+                if self.is_opaque_at(position, center) && other.is_opaque_at(other_position, center) {
+                    overlap_count += 1;
+                    overlap_x += center.0;
+                    overlap_y += center.1;
+                }
+            }
+        }
+
+        if overlap_count == 0 {
+            None
+        } else {
+            Some((overlap_x / overlap_count as f32, overlap_y / overlap_count as f32))
+        }
+    }
+
+    // First contact of two cars moving in a straight line during a step, from their start to their end position
+    // (top left corners of their textures). Returns the progress of the step at the contact, from 0 to 1,
+    // and the point of the contact: a fast car cannot go through another one between two steps
+    pub fn get_swept_collision(&self,
+                               start_position: (f32, f32),
+                               end_position: (f32, f32),
+                               other: &Hitbox,
+                               other_start_position: (f32, f32),
+                               other_end_position: (f32, f32),
+                               pixel_collision: bool) -> Option<(f32, (f32, f32))> {
+        let (x, y, width, height) = self.get_rectangle();
+        let (other_x, other_y, other_width, other_height) = other.get_rectangle();
+
+        // Position of this car relative to the other one, it changes linearly during the step
+        let relative_start = (start_position.0 - other_start_position.0, start_position.1 - other_start_position.1);
+        let relative_end = (end_position.0 - other_end_position.0, end_position.1 - other_end_position.1);
+
+        // Progress interval of the step where the rectangles overlap on each axis
+        let (enter_x, exit_x) = Hitbox::get_overlap_interval(relative_start.0, relative_end.0,
+                                                             other_x - x - width, other_x + other_width - x);
+        let (enter_y, exit_y) = Hitbox::get_overlap_interval(relative_start.1, relative_end.1,
+                                                             other_y - y - height, other_y + other_height - y);
+        let enter = enter_x.max(enter_y).max(0.0);
+        let exit = exit_x.min(exit_y).min(1.0);
+        if enter >= exit {
+            return None;
+        }
+
+        let get_positions = |progress: f32| (
+            Hitbox::interpolate(start_position, end_position, progress),
+            Hitbox::interpolate(other_start_position, other_end_position, progress),
+        );
+
+        if !pixel_collision {
+            // The rectangles touch at the contact, the point is the middle of the touching edges
+            let (position, other_position) = get_positions(enter);
+            let (left, top, right, bottom) = self.get_overlap(position, other, other_position);
+            return Some((enter, ((left + right) / 2.0, (top + bottom) / 2.0)));
+        }
+
+        // The opaque pixels are tested along the interval, with less than a pixel of movement between two tests
+        let movement = (relative_end.0 - relative_start.0).abs().max((relative_end.1 - relative_start.1).abs());
+        let test_count = ((exit - enter) * movement).ceil().max(1.0) as u32;
+        for test in 0..=test_count {
+            let progress = enter + (exit - enter) * test as f32 / test_count as f32;
+            let (position, other_position) = get_positions(progress);
+            if let Some(point) = self.get_collision(position, other, other_position, true) {
+                return Some((progress, point));
+            }
+        }
+        None
+    }
+
+    pub fn write_hash(&self, hasher: &mut StableHasher, pixel_collision: bool) {
+        // The size of the texture places the car on its lane
+        for value in [self.x, self.y, self.width, self.height, self.texture_width, self.texture_height] {
+            hasher.write_u32(value);
+        }
+        if pixel_collision {
+            for &opaque in &self.mask {
+                hasher.write_u8(opaque as u8);
+            }
+        }
+    }
+
+    // Progress interval (enter, exit) where a relative position going from `start` to `end`
+    // is strictly between `low` and `high`, it can go beyond 0 and 1
+    fn get_overlap_interval(start: f32, end: f32, low: f32, high: f32) -> (f32, f32) {
+        let speed = end - start;
+        if speed == 0.0 {
+            return if low < start && start < high {
+                (f32::NEG_INFINITY, f32::INFINITY)
+            } else {
+                (f32::INFINITY, f32::NEG_INFINITY)
+            };
+        }
+
+        let low_progress = (low - start) / speed;
+        let high_progress = (high - start) / speed;
+        (low_progress.min(high_progress), low_progress.max(high_progress))
+    }
+
+    // Intersection (left, top, right, bottom) of the rectangles on the screen, it is empty when left >= right or top >= bottom
+    fn get_overlap(&self, position: (f32, f32), other: &Hitbox, other_position: (f32, f32)) -> (f32, f32, f32, f32) {
+        let (x, y, width, height) = self.get_rectangle();
+        let (other_x, other_y, other_width, other_height) = other.get_rectangle();
+
+        (
+            (position.0 + x).max(other_position.0 + other_x),
+            (position.1 + y).max(other_position.1 + other_y),
+            (position.0 + x + width).min(other_position.0 + other_x + other_width),
+            (position.1 + y + height).min(other_position.1 + other_y + other_height),
+        )
+    }
+
+    fn interpolate(start: (f32, f32), end: (f32, f32), progress: f32) -> (f32, f32) {
+        (start.0 + (end.0 - start.0) * progress, start.1 + (end.1 - start.1) * progress)
+    }
+
+    // Whether a point of the screen is on an opaque pixel of the hitbox of a texture drawn at `position`
+    fn is_opaque_at(&self, position: (f32, f32), point: (f32, f32)) -> bool {
+        let x = point.0 - position.0 - self.x as f32;
+        let y = point.1 - position.1 - self.y as f32;
+        x >= 0.0 && y >= 0.0 && self.is_opaque(x as u32, y as u32)
+    }
+}
+
+impl CarHitboxes {
+    // The hitboxes are read from the files of the textures, the simulation without window needs them too
+    pub fn load(manifest: &AssetManifest, pack: Option<&AssetPack>) -> RustyResult<CarHitboxes> {
+        let player_car = AssetManager::load_hitboxes(manifest, pack, PLAYER_CAR_TEXTURE)?
+            .into_iter().next()
+            .ok_or_else(|| RustyError::Asset(format!("no texture for {}", PLAYER_CAR_TEXTURE)))?;
+        let bot_cars = AssetManager::load_hitboxes(manifest, pack, BOT_CAR_TEXTURES)?;
+        // The bot cars need at least one texture, the assets without one are refused
+        if bot_cars.is_empty() {
+            return Err(RustyError::Asset(format!("no texture for {}", BOT_CAR_TEXTURES)));
+        }
+
+        Ok(CarHitboxes {
+            player_car,
+            bot_cars,
+        })
+    }
+
+    pub fn get_bot_car(&self, texture_id: usize) -> &Hitbox {
+        &self.bot_cars[texture_id]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    // Car of 100x40 pixels: its hitbox is its whole texture
+    fn get_car() -> Hitbox {
+        Hitbox::from_size(100, 40)
+    }
+
+    // Diamond of 40x40 pixels, the corners of its texture are transparent
+    fn get_diamond() -> Hitbox {
+        let image = RgbaImage::from_fn(40, 40, |x, y| {
+            let distance = (x as f32 + 0.5 - 20.0).abs() + (y as f32 + 0.5 - 20.0).abs();
+            if distance <= 20.0 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 0, 0]) }
+        });
+        Hitbox::from_image(&image, None)
+    }
+
+    #[test]
+    fn overlap_interval() {
+        // From 0 to 100, the position is between 25 and 50 from 0.25 to 0.5 of the step
+        assert_eq!(Hitbox::get_overlap_interval(0.0, 100.0, 25.0, 50.0), (0.25, 0.5));
+        assert_eq!(Hitbox::get_overlap_interval(100.0, 0.0, 25.0, 50.0), (0.5, 0.75));
+        // Without movement, the position is always or never between the limits
+        assert_eq!(Hitbox::get_overlap_interval(30.0, 30.0, 25.0, 50.0), (f32::NEG_INFINITY, f32::INFINITY));
+        assert_eq!(Hitbox::get_overlap_interval(50.0, 50.0, 25.0, 50.0), (f32::INFINITY, f32::NEG_INFINITY));
+    }
+
+    #[test]
+    fn fast_car_going_through_another_one_collides() {
+        let (bot_car, player_car) = (get_car(), get_car());
+
+        // The bot car jumps from the right of the player car to its left during the step
+        let (bot_start, bot_end) = ((600.0, 0.0), (50.0, 0.0));
+        let player_position = (300.0, 0.0);
+        assert_eq!(bot_car.get_collision(bot_end, &player_car, player_position, false), None);
+
+        let (progress, (point_x, point_y)) = bot_car.get_swept_collision(bot_start, bot_end, &player_car, player_position, player_position, false).unwrap();
+        // The contact is when the left of the bot car reaches the right of the player car
+        assert!((progress - 200.0 / 550.0).abs() < 1e-4);
+        assert!((point_x - 400.0).abs() < 1e-3);
+        assert_eq!(point_y, 20.0);
+    }
+
+    #[test]
+    fn lane_change_collides_with_the_car_of_the_next_lane() {
+        let (bot_car, player_car) = (get_car(), get_car());
+
+        // The bot car stays at the same place, the player car goes up from the next lane
+        let bot_position = (300.0, 100.0);
+        let (player_start, player_end) = ((300.0, 200.0), (300.0, 100.0));
+        let (progress, (_, point_y)) = bot_car.get_swept_collision(bot_position, bot_position, &player_car, player_start, player_end, false).unwrap();
+        assert!((progress - 0.6).abs() < 1e-4);
+        assert!((point_y - 140.0).abs() < 1e-3);
+
+        // Going down, away from the bot car, never touches it
+        let player_end = (300.0, 300.0);
+        assert_eq!(bot_car.get_swept_collision(bot_position, bot_position, &player_car, player_start, player_end, false), None);
+    }
+
+    #[test]
+    fn near_misses_do_not_collide() {
+        let (bot_car, player_car) = (get_car(), get_car());
+        let player_position = (300.0, 0.0);
+
+        // The bot car stops exactly at the right of the player car
+        assert_eq!(bot_car.get_swept_collision((600.0, 0.0), (400.0, 0.0), &player_car, player_position, player_position, false), None);
+        // The bot car goes through the place of the player car, on the next lane
+        assert_eq!(bot_car.get_swept_collision((600.0, 40.0), (0.0, 40.0), &player_car, player_position, player_position, false), None);
+        // The player car leaves the lane just before the bot car passes
+        assert_eq!(bot_car.get_swept_collision((600.0, 0.0), (500.0, 0.0), &player_car, (300.0, 0.0), (300.0, 50.0), false), None);
+    }
+
+    #[test]
+    fn pixel_collision_follows_the_opaque_pixels() {
+        let diamond = get_diamond();
+        assert_eq!(diamond.get_rectangle(), (0.0, 0.0, 40.0, 40.0));
+
+        // The rectangles overlap at the corners, the diamonds do not touch
+        let (position, other_position) = ((0.0, 0.0), (30.0, 30.0));
+        assert!(diamond.get_collision(position, &diamond, other_position, false).is_some());
+        assert_eq!(diamond.get_collision(position, &diamond, other_position, true), None);
+
+        // A diamond sliding along the side of the other one touches its rectangle, not its pixels
+        let (start, end) = ((45.0, 5.0), (5.0, 45.0));
+        assert!(diamond.get_swept_collision(start, end, &diamond, (0.0, 0.0), (0.0, 0.0), false).is_some());
+        assert_eq!(diamond.get_swept_collision(start, end, &diamond, (0.0, 0.0), (0.0, 0.0), true), None);
+
+        // Head-on, the tips of the diamonds touch after the rectangles
+        let (start, end) = ((100.0, 0.0), (-100.0, 0.0));
+        let (rectangle_progress, _) = diamond.get_swept_collision(start, end, &diamond, (0.0, 0.0), (0.0, 0.0), false).unwrap();
+        let (pixel_progress, (point_x, point_y)) = diamond.get_swept_collision(start, end, &diamond, (0.0, 0.0), (0.0, 0.0), true).unwrap();
+        assert!((rectangle_progress - 0.3).abs() < 1e-4);
+        assert!(pixel_progress > rectangle_progress && pixel_progress < 0.5);
+        assert!((point_x - 40.0).abs() < 1.5);
+        assert!((point_y - 20.0).abs() < 1.5);
+    }
+}
@@ -1,26 +1,22 @@
-use macroquad::prelude::*;
-
 use crate::WINDOW_WIDTH;
 
 pub mod bot_car;
 pub mod player_car;
 pub mod bot_manager;
+pub mod hitbox;
 
+// Height of the default player car, the lanes are far enough from each other for it
 pub const PLAYER_CAR_HEIGHT: f32 = 73.0;
-pub const PLAYER_CAR_WIDTH: f32 = 155.0;
-pub const BOT_CAR_WIDTH: f32 = 140.0;
 pub const PLAYER_CAR_X_POSITION: f32 = WINDOW_WIDTH / 4.0;
+// Rotation of the player car in the middle of a lane change, in radians
+pub const PLAYER_CAR_MAX_TILT: f32 = 0.12;
 
-#[derive(PartialEq, Hash, Eq, Clone, Copy)]
-pub enum Way {
-    Upper,
-    Center,
-    Lower,
-}
+/// Index of a lane, from the top of the road, the number of lanes comes from the configuration
+#[derive(PartialEq, Hash, Eq, Clone, Copy, Debug)]
+pub struct Way(pub usize);
 
 /// Trait for all cars, but it is not a entire generic type
 /// to separate the player car and the bot car
 pub trait Car {
-    fn get_texture(&self) -> Texture2D;
     fn get_way(&self) -> Way;
-}
\ No newline at end of file
+}
@@ -1,33 +1,106 @@
-use macroquad::prelude::{FileError, load_texture, Texture2D};
+use std::f32::consts::PI;
 
-use crate::game::car::{Car, Way};
-
-const PLAYER_CAR_PATH: &str = "assets/cars/playerCar.png";
+use crate::game::car::{Car, PLAYER_CAR_MAX_TILT, Way};
 
 #[derive(Clone)]
 pub struct PlayerCar {
-    texture: Texture2D,
+    // Lane where the car is, or where it goes during a lane change
     way: Way,
+    y_position: f32,
+    // Position before the last update, used to interpolate the drawing between two simulation steps
+    previous_y_position: f32,
+    // Position of the car when the lane change started, it can be between two lanes
+    start_y_position: f32,
+    // Progress of the lane change, from 0 (started) to 1 (lane reached)
+    lane_change_progress: f32,
+    // Rotation of the car in radians, the car leans towards its new lane
+    tilt: f32,
 }
 
 impl PlayerCar {
-    pub async fn new() -> Result<PlayerCar, FileError> {
-        let background_texture = load_texture(PLAYER_CAR_PATH).await?;
-        Ok(PlayerCar {
-            texture: background_texture,
-            way: Way::Center,
-        })
+    // The car starts in the middle lane
+    pub fn new(lane_positions: &[f32]) -> PlayerCar {
+        let way = Way(lane_positions.len() / 2);
+        let y_position = lane_positions[way.0];
+
+        PlayerCar {
+            way,
+            y_position,
+            previous_y_position: y_position,
+            start_y_position: y_position,
+            lane_change_progress: 1.0,
+            tilt: 0.0,
+        }
     }
 
+    // The car leaves its current position, even in the middle of another lane change
     pub fn set_way(&mut self, way: Way) {
         self.way = way;
+        self.start_y_position = self.y_position;
+        self.lane_change_progress = 0.0;
+    }
+
+    // Moves the car towards its lane, a lane change lasts `lane_change_duration` seconds (0 is instant)
+    pub fn update_position(&mut self, delta_time: f32, lane_change_duration: f32, lane_positions: &[f32]) {
+        self.previous_y_position = self.y_position;
+
+        self.lane_change_progress = if lane_change_duration > 0.0 {
+            (self.lane_change_progress + delta_time / lane_change_duration).min(1.0)
+        } else {
+            1.0
+        };
+
+        let target_y_position = lane_positions[self.way.0];
+        let progress = self.lane_change_progress;
+        if progress < 1.0 {
+            // Smoothstep easing: the car speeds up when it leaves its lane and slows down before the next one
+            let eased_progress = progress * progress * (3.0 - 2.0 * progress);
+            self.y_position = self.start_y_position + (target_y_position - self.start_y_position) * eased_progress;
+
+            // The tilt follows the vertical speed of the car, it is the highest in the middle of the change
+            let direction = (target_y_position - self.start_y_position).signum();
+            self.tilt = PLAYER_CAR_MAX_TILT * direction * (PI * progress).sin();
+        } else {
+            self.y_position = target_y_position;
+            self.tilt = 0.0;
+        }
+
+        // An instant lane change jumps over the road between the lanes, the car does not slide on it
+        if lane_change_duration <= 0.0 {
+            self.previous_y_position = self.y_position;
+        }
+    }
+
+    // Vertical position of the center of the car before the last update
+    pub fn get_previous_y_position(&self) -> f32 {
+        self.previous_y_position
+    }
+
+    // Puts the car back where it was at `progress` (0 to 1) of the last update, at the moment of a collision
+    pub fn rewind_position(&mut self, progress: f32) {
+        self.y_position = self.get_interpolated_y_position(progress);
+    }
+
+    // Vertical position of the center of the car
+    pub fn get_y_position(&self) -> f32 {
+        self.y_position
+    }
+
+    // Position between the previous and the current step, `interpolation` goes from 0.0 to 1.0
+    pub fn get_interpolated_y_position(&self, interpolation: f32) -> f32 {
+        self.previous_y_position + (self.y_position - self.previous_y_position) * interpolation
+    }
+
+    pub fn is_changing_lane(&self) -> bool {
+        self.lane_change_progress < 1.0
+    }
+
+    pub fn get_tilt(&self) -> f32 {
+        self.tilt
     }
 }
 
 impl Car for PlayerCar {
-    fn get_texture(&self) -> Texture2D {
-        self.texture
-    }
     fn get_way(&self) -> Way {
         self.way
     }
@@ -0,0 +1,97 @@
+use std::hash::Hasher;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::stable_hasher::StableHasher;
+
+// Shape of the speed progression, the time is the simulated time of the run in seconds
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SpeedCurve {
+    // The speed increases continuously
+    Linear { start: f32, increase_per_second: f32 },
+    // The speed increases by `increase` every `interval` seconds
+    Stepped { start: f32, increase: f32, interval: f32 },
+    // The speed increases quickly at the beginning then slower and slower,
+    // `time_scale` is the time needed to earn the first `factor` * ln(2)
+    Logarithmic { start: f32, factor: f32, time_scale: f32 },
+}
+
+/// Speed of the bot cars depending on the time spent in the run, the same time
+/// always gives the same speed so the difficulty can be reproduced
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DifficultyCurve {
+    pub speed_curve: SpeedCurve,
+    // The speed never goes above this value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_speed: Option<f32>,
+}
+
+impl DifficultyCurve {
+    pub fn get_speed(&self, game_time: f32) -> f32 {
+        let game_time = game_time.max(0.0);
+
+        let speed = match self.speed_curve {
+            SpeedCurve::Linear { start, increase_per_second } => {
+                start + increase_per_second * game_time
+            }
+            SpeedCurve::Stepped { start, increase, interval } => {
+                if interval > 0.0 {
+                    start + increase * (game_time / interval).floor()
+                } else {
+                    start
+                }
+            }
+            SpeedCurve::Logarithmic { start, factor, time_scale } => {
+                if time_scale > 0.0 {
+                    start + factor * (game_time / time_scale).ln_1p()
+                } else {
+                    start
+                }
+            }
+        };
+
+        match self.max_speed {
+            Some(max_speed) => speed.min(max_speed),
+            None => speed,
+        }
+    }
+
+    // The progression of the curve is kept, only its start changes
+    pub fn set_start_speed(&mut self, start_speed: f32) {
+        match &mut self.speed_curve {
+            SpeedCurve::Linear { start, .. } => *start = start_speed,
+            SpeedCurve::Stepped { start, .. } => *start = start_speed,
+            SpeedCurve::Logarithmic { start, .. } => *start = start_speed,
+        }
+    }
+
+    pub fn get_start_speed(&self) -> f32 {
+        self.get_speed(0.0)
+    }
+
+    pub fn write_hash(&self, hasher: &mut StableHasher) {
+        match self.speed_curve {
+            SpeedCurve::Linear { start, increase_per_second } => {
+                hasher.write_u8(0);
+                hasher.write_f32(start);
+                hasher.write_f32(increase_per_second);
+            }
+            SpeedCurve::Stepped { start, increase, interval } => {
+                hasher.write_u8(1);
+                hasher.write_f32(start);
+                hasher.write_f32(increase);
+                hasher.write_f32(interval);
+            }
+            SpeedCurve::Logarithmic { start, factor, time_scale } => {
+                hasher.write_u8(2);
+                hasher.write_f32(start);
+                hasher.write_f32(factor);
+                hasher.write_f32(time_scale);
+            }
+        }
+
+        hasher.write_f32(self.max_speed.unwrap_or(f32::INFINITY));
+    }
+}
@@ -1,305 +1,714 @@
-use std::sync::{Arc, Mutex};
-use std::sync::mpsc::Receiver;
-
-use macroquad::prelude::get_frame_time;
-
-use crate::config::{SPEED_INCREASE_TIMING, SPEED_INCREASE_VALUE, START_GAME_SPEED};
-use crate::game::car::{Car, Way};
-use crate::game::car::bot_manager::BotManager;
-use crate::game::car::player_car::PlayerCar;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{error, info, warn};
+use macroquad::prelude::{get_char_pressed, get_frame_time, get_time, is_key_pressed, KeyCode};
+
+use crate::config::{ASSET_DIRECTORY, DEV_WATCH_INTERVAL, FULLSCREEN_KEY, HITBOX_OVERLAY_KEY, MAX_FRAME_DURATION, MAX_REPLAY_COUNT, SIMULATION_STEP_DURATION};
+use crate::config::config_file::{Config, GameConfig, KeysConfig};
+use crate::game::assets::asset_manager::{AssetManager, GAME_MUSIC};
+use crate::game::assets::asset_pack::AssetPack;
+use crate::game::assets::manifest::AssetManifest;
+use crate::game::car::hitbox::CarHitboxes;
+use crate::game::game_mode::GameMode;
 use crate::game::graphics::graphics_manager::GraphicsManager;
+use crate::game::graphics::virtual_screen::VirtualScreen;
+use crate::game::high_scores::{HighScore, HighScoreTable, MAX_NAME_LENGTH};
+use crate::game::replay::{Replay, ReplayPlayer};
+use crate::game::rhythm::BeatTrack;
+use crate::game::rhythm::level::{Level, LevelTrack};
+use crate::game::settings_menu::{SettingsChange, SettingsMenu};
+use crate::game::simulation::{GameState, Simulation, SimulationEvent};
 use crate::game::sounds::sounds_manager::{SoundsManager, SoundType};
 use crate::keyboard::GameAction;
-use crate::utils::rusty_error::{LockError, RustyError, RustyResult};
-use crate::utils::rusty_error::RustyError::RustyLock;
-use crate::utils::timer::{Timer, TimerData};
-
-const PLAYER_INPUT_AND_CAR_REACTION: [(GameAction, Way, Way); 4] = [
-    (GameAction::Up, Way::Center, Way::Upper),
-    (GameAction::Up, Way::Lower, Way::Center),
-    (GameAction::Down, Way::Upper, Way::Center),
-    (GameAction::Down, Way::Center, Way::Lower),
-];
-
-#[derive(Clone, PartialEq, Copy)]
-enum GameState {
-    NotStarted,
-    Running,
-    Pause,
-    GameOver,
+use crate::keyboard::input_queue::InputQueue;
+use crate::utils::file_watcher::FileWatcher;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+const DEFAULT_PLAYER_NAME: &str = "Player";
+
+/// Choices of the command line for this session, they are never saved in the configuration
+#[derive(Default)]
+pub struct GameOptions {
+    pub seed: Option<u64>,
+    pub replay: Option<Replay>,
+    // Replaces the random traffic and the difficulty curve
+    pub level: Option<Level>,
+    // Replaces the pack of the configuration
+    pub asset_pack: Option<String>,
+    pub start_speed: Option<f32>,
+    pub mode: Option<GameMode>,
+    pub mute: bool,
+    pub fullscreen: bool,
+    pub dev_mode: bool,
+}
+
+impl GameOptions {
+    // The game settings of the configuration, changed by the command line
+    pub fn get_game_config(&self, game_config: &GameConfig) -> GameConfig {
+        let mut game_config = game_config.clone();
+        if let Some(start_speed) = self.start_speed {
+            game_config.difficulty.set_start_speed(start_speed);
+        }
+        if let Some(mode) = self.mode {
+            game_config.mode = mode;
+        }
+        game_config
+    }
 }
 
 pub struct Game {
-    receiver_input: Arc<Mutex<Receiver<GameAction>>>,
+    config: Config,
+    // The configuration is written back in this file when it is changed in game
+    config_path: PathBuf,
+    // The replay has been taken out to be played
+    options: GameOptions,
+    input_queue: InputQueue,
     graphics_manager: GraphicsManager,
-    pub player_car: PlayerCar,
-    bot_manager: BotManager,
-    speed_timer: Timer,
-    score: u32,
-    session_record: u32,
-    speed: Arc<Mutex<f32>>,
-    game_state: GameState,
-    game_previous_state: GameState,
-    game_over_collision: Option<(Way, f32)>,
+    virtual_screen: VirtualScreen,
+    simulation: Simulation,
     sounds_manager: SoundsManager,
+    // Time not simulated yet, it is consumed by steps of SIMULATION_STEP_DURATION
+    time_accumulator: f32,
+    // Actions of the current run, saved at the end of the run or when the player quits it
+    replay_recorder: Option<Replay>,
+    // Start of the current run in seconds since the Unix epoch, the replay is named from it
+    run_start_timestamp: u64,
+    // When a replay is played, the actions of the run come from it instead of the keyboard
+    replay_player: Option<ReplayPlayer>,
+    high_scores: HighScoreTable,
+    // High score of the last run waiting for the name of the player
+    pending_high_score: Option<HighScore>,
+    show_high_scores: bool,
+    // Debug overlay drawing the hitboxes of the cars
+    show_hitboxes: bool,
+    settings_menu: Option<SettingsMenu>,
+    // Pack of the loaded assets, the default assets without it
+    asset_pack: Option<String>,
+    // The assets are loaded asynchronously, the main loop reloads them with this pack between two frames
+    requested_asset_pack: Option<Option<String>>,
+    // In development mode, the files are watched to reload them while the game runs
+    asset_watcher: Option<FileWatcher>,
+    config_watcher: Option<FileWatcher>,
+    next_watch_time: f64,
 }
 
 impl Game {
-    pub async fn new(receiver_key: Receiver<GameAction>) -> RustyResult<Game> {
-        let graphics_manager: GraphicsManager = GraphicsManager::new().await?;
-        let mut sounds_manager: SoundsManager = SoundsManager::new().await?;
-
-        let player_car: PlayerCar = PlayerCar::new().await?;
+    pub async fn new(config: Config, config_path: PathBuf, mut options: GameOptions) -> RustyResult<Game> {
+        // The pack of the command line replaces the one of the configuration
+        let mut asset_pack = options.asset_pack.clone().or_else(|| config.assets.pack.clone());
+
+        // All the assets are loaded now, the game never waits for the disk during a run
+        let (assets, hitboxes, beat_track) = match Game::load_assets(&config, asset_pack.as_deref(), options.level.as_ref()).await {
+            Ok(loaded_assets) => loaded_assets,
+            // A pack chosen in the settings may have been removed since, the game starts with the default assets
+            Err(e) if asset_pack.is_some() && asset_pack == config.assets.pack => {
+                warn!("Error loading the asset pack, the default assets are used: {}", e);
+                asset_pack = None;
+                Game::load_assets(&config, None, options.level.as_ref()).await?
+            }
+            Err(e) => return Err(e),
+        };
+        let mut sounds_manager: SoundsManager = SoundsManager::new(&config.sounds, &assets)?;
+        let mut graphics_manager: GraphicsManager = GraphicsManager::new(assets, config.keys.get_key_bindings())?;
+        graphics_manager.set_hitboxes(&hitboxes);
+
+        // The replay imposes its seed to get the same traffic
+        let replay = options.replay.take();
+        let seed = replay.as_ref().map_or(options.seed, |replay| Some(replay.seed));
+        let simulation: Simulation = Simulation::new(&options.get_game_config(&config.game),
+                                                     hitboxes,
+                                                     beat_track,
+                                                     options.level.as_ref().map(LevelTrack::from_level),
+                                                     seed);
+
+        if let Some(replay) = &replay {
+            if replay.config_hash != simulation.get_config_hash() {
+                warn!("The replay was recorded with other game settings, the run may be different");
+            }
+        }
 
-        let start_speed: Arc<Mutex<f32>> = Arc::new(Mutex::new(START_GAME_SPEED));
+        // A broken high score file must not prevent to play
+        let high_scores = HighScoreTable::load().unwrap_or_else(|e| {
+            error!("Error loading high scores: {}", e);
+            HighScoreTable::new()
+        });
 
-        // The timer data is used to share the current speed between the timer and the game
-        let timer_data: Arc<Mutex<TimerData>> = Arc::new(Mutex::new(
-            TimerData::GameSpeed {
-                speed: Arc::clone(&start_speed)
-            }
-        ));
+        let virtual_screen = VirtualScreen::new(options.fullscreen);
+        let input_queue = InputQueue::new(config.keys.get_key_bindings(), &config.gamepad);
 
+        if options.mute && !sounds_manager.is_muted() {
+            sounds_manager.set_mute_songs();
+        }
         sounds_manager.play_sound(SoundType::Menu, true);
 
+        let mut asset_watcher = None;
+        let mut config_watcher = None;
+        if options.dev_mode {
+            info!("Development mode: the assets and {} are reloaded when they change", config_path.display());
+            asset_watcher = Some(FileWatcher::new(Game::get_asset_watch_paths(&config, asset_pack.as_deref())));
+            config_watcher = Some(FileWatcher::new(vec![config_path.clone()]));
+        }
+
         Ok(Game {
-            receiver_input: Arc::new(Mutex::new(receiver_key)),
+            config,
+            config_path,
+            options,
+            input_queue,
             graphics_manager,
-            player_car,
-            bot_manager: BotManager::new(),
-            speed_timer: Timer::new(Game::speed_up, timer_data),
-            score: 0,
-            session_record: 0,
-            speed: Arc::clone(&start_speed),
-            game_state: GameState::NotStarted,
-            game_previous_state: GameState::NotStarted,
-            game_over_collision: None,
+            virtual_screen,
+            simulation,
             sounds_manager,
+            time_accumulator: 0.0,
+            replay_recorder: None,
+            run_start_timestamp: 0,
+            replay_player: replay.map(ReplayPlayer::new),
+            high_scores,
+            pending_high_score: None,
+            show_high_scores: false,
+            show_hitboxes: false,
+            settings_menu: None,
+            asset_pack,
+            requested_asset_pack: None,
+            asset_watcher,
+            config_watcher,
+            next_watch_time: 0.0,
         })
     }
 
-    pub fn start(&mut self) {
-        self.score = 0;
+    async fn load_assets(config: &Config, asset_pack: Option<&str>, level: Option<&Level>) -> RustyResult<(AssetManager, CarHitboxes, BeatTrack)> {
+        let (manifest, pack) = AssetManifest::load_for_game(config, asset_pack, level)?;
+        let assets = AssetManager::load(&manifest, pack.as_ref()).await?;
+        let hitboxes = CarHitboxes::load(&manifest, pack.as_ref())?;
+        let beat_track = AssetManager::load_beat_track(&manifest, pack.as_ref(), GAME_MUSIC)?;
+        Ok((assets, hitboxes, beat_track))
+    }
 
-        self.speed_timer.start(SPEED_INCREASE_TIMING);
-        self.game_state = GameState::Running;
+    pub fn is_asset_reload_requested(&self) -> bool {
+        self.requested_asset_pack.is_some()
     }
 
-    pub async fn run(&mut self) -> RustyResult<bool> {
-        let game_action: GameAction = self.get_game_action()?;
+    // On error the current assets are kept, the player can choose another pack
+    pub async fn reload_assets(&mut self) {
+        if let Some(asset_pack) = self.requested_asset_pack.take() {
+            match self.try_reload_assets(asset_pack.as_deref()).await {
+                Ok(()) => {
+                    // Another pack may have been chosen, its files are watched instead
+                    if self.asset_watcher.is_some() {
+                        self.asset_watcher = Some(FileWatcher::new(Game::get_asset_watch_paths(&self.config, asset_pack.as_deref())));
+                    }
+                    self.asset_pack = asset_pack;
+                }
+                Err(e) => error!("Error reloading the assets: {}", e),
+            }
+        }
+    }
 
-        // If the game is not running, player can quit the game
-        if game_action == GameAction::Quit && self.game_state != GameState::Running {
-            return Ok(true);
+    async fn try_reload_assets(&mut self, asset_pack: Option<&str>) -> RustyResult<()> {
+        let (assets, hitboxes, beat_track) = Game::load_assets(&self.config, asset_pack, self.options.level.as_ref()).await?;
+        if let Some(level) = &self.options.level {
+            level.check_game(self.config.game.lanes.len(), hitboxes.bot_cars.len()).map_err(RustyError::Level)?;
         }
+        let mut sounds_manager = SoundsManager::new(&self.config.sounds, &assets)?;
 
-        if game_action == GameAction::Mute {
-            self.sounds_manager.set_mute_songs();
+        // The sounds stay muted or not as they were
+        if sounds_manager.is_muted() != self.sounds_manager.is_muted() {
+            sounds_manager.set_mute_songs();
         }
 
-        // The delta time is used to move the background and the bot cars
-        let delta_time: f32 = get_frame_time();
+        self.sounds_manager.stop_all_sounds();
+        self.sounds_manager = sounds_manager;
+        self.graphics_manager.set_assets(assets)?;
+        self.graphics_manager.set_hitboxes(&hitboxes);
 
-        // Here we need to pay attention to the sequence of draw functions due to the
-        // superposition of the elements
+        let config_hash = self.simulation.get_config_hash();
+        self.simulation.set_hitboxes(hitboxes);
+        self.simulation.set_beat_track(beat_track);
+        self.check_replay_recording(config_hash);
 
-        let entrance: bool = self.game_state != self.game_previous_state;
-        self.game_previous_state = self.game_state;
+        if let Some((sound_type, play_loop)) = Game::get_state_sound(self.simulation.get_state()) {
+            self.sounds_manager.play_sound(sound_type, play_loop);
+        }
 
-        match self.game_state {
-            GameState::NotStarted => {
-                if entrance {
-                    self.sounds_manager.play_sound(SoundType::Menu, true);
-                }
+        Ok(())
+    }
 
-                if game_action == GameAction::PauseResume {
-                    self.sounds_manager.stop_sound(SoundType::Menu);
-                    self.start();
-                } else {
-                    self.graphics_manager.background.move_texture(delta_time);
-                    self.graphics_manager.draw_new_game();
-                }
+    // The sound files chosen in the configuration may be outside of the asset directory
+    fn get_asset_watch_paths(config: &Config, asset_pack: Option<&str>) -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(ASSET_DIRECTORY)];
+        paths.extend(AssetPack::get_pack_directories());
+
+        if let Some(asset_pack) = asset_pack {
+            paths.push(PathBuf::from(asset_pack));
+        }
+        for (_, sound_config) in config.sounds.get_sounds() {
+            if let Some(path) = &sound_config.path {
+                paths.push(PathBuf::from(path));
             }
-            GameState::Running => {
-                if entrance {
-                    self.sounds_manager.play_sound(SoundType::Game, true);
-                }
+        }
 
-                self.move_player_car(game_action);
+        paths
+    }
 
-                {
-                    let current_speed = self.speed.lock().map_err(|e| RustyLock(LockError {
-                        message: format!("Impossible to lock the access to the current score: {}", e),
-                    }))?;
+    // Checks the watched files, at most once per DEV_WATCH_INTERVAL because each check reads the directories
+    fn watch_files(&mut self) {
+        if get_time() < self.next_watch_time {
+            return;
+        }
+        self.next_watch_time = get_time() + DEV_WATCH_INTERVAL;
 
-                    // The score is calculated with the current speed
-                    self.score += (0.005 * *current_speed) as u32;
+        if self.config_watcher.as_mut().is_some_and(FileWatcher::has_changed) {
+            self.reload_config();
+        }
 
-                    // The background and the bot cars are moved with the current speed
-                    // But the background is moved with a speed of 80% of the current speed
-                    self.graphics_manager.background.set_speed(*current_speed * 0.8);
-                    self.graphics_manager.background.move_texture(delta_time);
-                    for bot_car in self.bot_manager.bot_car_list.iter_mut() {
-                        self.graphics_manager.draw_bot_car(bot_car);
-                        bot_car.set_speed(*current_speed);
-                    }
-                }
+        if self.asset_watcher.as_mut().is_some_and(FileWatcher::has_changed) {
+            info!("Assets changed, reloading them");
+            self.requested_asset_pack.get_or_insert(self.asset_pack.clone());
+        }
+    }
 
-                self.graphics_manager.draw_player_car(&self.player_car);
-                self.graphics_manager.draw_score(self.score);
+    // The state of the game is kept, only the settings are replaced.
+    // A broken file is reported and the current configuration is kept until it is fixed
+    fn reload_config(&mut self) {
+        let config = match Config::load(&self.config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Error reloading the configuration, the current one is kept: {}", e);
+                return;
+            }
+        };
+        if let Some(level) = &self.options.level {
+            if let Err(e) = level.check_game(config.game.lanes.len(), self.simulation.get_hitboxes().bot_cars.len()) {
+                error!("Error reloading the configuration, the current one is kept: {}", e);
+                return;
+            }
+        }
+        info!("Configuration reloaded");
 
-                // The player car is colliding with a bot car ?
-                let car_colliding = self.manage_bot_cars(delta_time).await?;
-                if let Some((way, x_position)) = car_colliding {
-                    self.game_state = GameState::GameOver;
-                    self.game_over_collision = Some((way, x_position));
-                }
+        let key_bindings = config.keys.get_key_bindings();
+        self.input_queue.set_key_bindings(key_bindings.clone());
+        self.graphics_manager.set_key_bindings(key_bindings);
 
-                if game_action == GameAction::PauseResume {
-                    // No sound in pause menu
-                    self.sounds_manager.stop_sound(SoundType::Game);
-                    self.game_state = GameState::Pause;
-                }
+        self.input_queue.set_dead_zone(config.gamepad.dead_zone);
+        if config.gamepad.enabled != self.config.gamepad.enabled {
+            warn!("The gamepad support will be changed at the next start");
+        }
+
+        let config_hash = self.simulation.get_config_hash();
+        self.simulation.set_game_config(&self.options.get_game_config(&config.game));
+        self.check_replay_recording(config_hash);
+
+        if config.sounds.muted != self.config.sounds.muted && config.sounds.muted != self.sounds_manager.is_muted() {
+            self.sounds_manager.set_mute_songs();
+        }
+
+        // The sounds are loaded with the assets, they are reloaded together
+        if config.assets.pack != self.config.assets.pack {
+            self.requested_asset_pack = Some(config.assets.pack.clone());
+        } else if config.sounds != self.config.sounds {
+            self.requested_asset_pack.get_or_insert(self.asset_pack.clone());
+        }
+
+        self.config = config;
+    }
+
+    // A run whose settings changed could not be replayed, it is not recorded
+    fn check_replay_recording(&mut self, previous_config_hash: u64) {
+        if self.simulation.get_config_hash() != previous_config_hash && self.replay_recorder.take().is_some() {
+            warn!("The game settings changed during the run, it will not be recorded");
+        }
+    }
+
+    pub fn run(&mut self) -> RustyResult<bool> {
+        // The frame time is only used to know how many simulation steps are needed,
+        // the simulation itself always advances with the same step duration
+        let frame_time: f32 = get_frame_time().min(MAX_FRAME_DURATION);
+        self.time_accumulator += frame_time;
+
+        self.input_queue.update();
+
+        // The fullscreen is toggled on every screen, it is not an action of the game
+        if is_key_pressed(FULLSCREEN_KEY) {
+            self.virtual_screen.toggle_fullscreen();
+        }
+        if is_key_pressed(HITBOX_OVERLAY_KEY) {
+            self.show_hitboxes = !self.show_hitboxes;
+        }
+
+        if self.config_watcher.is_some() {
+            self.watch_files();
+        }
+
+        if self.pending_high_score.is_some() {
+            self.update_name_entry();
+        }
+
+        if self.settings_menu.is_some() {
+            self.update_settings_menu();
+        }
+
+        let mut quit_game = false;
+        while self.time_accumulator >= SIMULATION_STEP_DURATION && !quit_game {
+            // One action is given to each step, the others wait in the queue for the next steps
+            let player_action: GameAction = self.input_queue.pop_event().map_or(GameAction::None, |event| event.action);
+            let player_action: GameAction = self.filter_menu_action(player_action);
+
+            if player_action == GameAction::Mute {
+                self.sounds_manager.set_mute_songs();
+
+                self.config.sounds.muted = self.sounds_manager.is_muted();
+                self.save_config();
             }
-            GameState::Pause => {
-                if entrance {
-                    // The timer is stopped to avoid to increase the speed while the game is paused
-                    self.speed_timer.stop();
+
+            let game_action: GameAction = self.get_step_action(player_action);
+            self.record_action(game_action);
+
+            for event in self.simulation.step(SIMULATION_STEP_DURATION, game_action)? {
+                match event {
+                    SimulationEvent::StateChanged { previous, current } => {
+                        self.on_state_changed(previous, current);
+                    }
+                    SimulationEvent::QuitRequested => {
+                        // A run quit from the pause is recorded without its final score
+                        self.save_replay(None);
+                        quit_game = true;
+                    }
                 }
+            }
 
-                // Draw game situation during the pause
+            self.time_accumulator -= SIMULATION_STEP_DURATION;
+        }
 
-                self.graphics_manager.background.draw();
-                self.graphics_manager.draw_score(self.score);
+        // Part of the next step already elapsed, used to draw the cars between two steps
+        let interpolation: f32 = self.time_accumulator / SIMULATION_STEP_DURATION;
+        self.virtual_screen.begin();
+        self.draw(frame_time, interpolation);
+        self.virtual_screen.end();
 
-                for bot_car in self.bot_manager.bot_car_list.iter_mut() {
-                    self.graphics_manager.draw_bot_car(bot_car);
-                }
+        Ok(quit_game)
+    }
+
+    fn save_config(&mut self) {
+        if let Err(e) = self.config.save(&self.config_path) {
+            error!("Error saving the configuration: {}", e);
+        }
 
-                self.graphics_manager.draw_player_car(&self.player_car);
-                self.graphics_manager.draw_pause(self.session_record);
+        // The changes made in game are already applied, they are not reloaded
+        if let Some(config_watcher) = self.config_watcher.as_mut() {
+            config_watcher.has_changed();
+        }
+    }
 
-                if game_action == GameAction::PauseResume {
-                    self.game_state = GameState::Running;
-                    self.speed_timer.start(SPEED_INCREASE_TIMING);
-                }
+    // Some actions are used by the screens drawn over the simulation and never reach it
+    fn filter_menu_action(&mut self, player_action: GameAction) -> GameAction {
+        let mut game_action = player_action;
+
+        if self.pending_high_score.is_some() {
+            // The keys typed for the name are not actions
+            game_action = GameAction::None;
+        } else if self.settings_menu.is_some() {
+            // The settings menu reads its own keys, only the settings key leaves it
+            if player_action == GameAction::Settings && !self.settings_menu.as_ref().is_some_and(SettingsMenu::is_waiting_for_key) {
+                self.settings_menu = None;
             }
-            GameState::GameOver => {
-                if entrance {
-                    self.sounds_manager.stop_sound(SoundType::Game);
-                    self.sounds_manager.play_sound(SoundType::GameOver, false);
-                    self.stop();
+            game_action = GameAction::None;
+        } else if self.simulation.get_state() == GameState::NotStarted {
+            if self.show_high_scores {
+                // Any menu key leaves the high score screen
+                if matches!(player_action, GameAction::HighScores | GameAction::PauseResume | GameAction::Quit) {
+                    self.show_high_scores = false;
+                    game_action = GameAction::None;
                 }
+            } else if player_action == GameAction::HighScores {
+                self.show_high_scores = true;
+                game_action = GameAction::None;
+            } else if player_action == GameAction::Settings {
+                self.settings_menu = Some(SettingsMenu::new(AssetPack::get_available_packs()));
+                game_action = GameAction::None;
+            }
+        }
 
-                // Draw game situation at the end of the game
-
-                self.graphics_manager.background.draw();
+        game_action
+    }
 
-                for bot_car in self.bot_manager.bot_car_list.iter_mut() {
-                    self.graphics_manager.draw_bot_car(bot_car);
+    fn update_name_entry(&mut self) {
+        if let Some(high_score) = self.pending_high_score.as_mut() {
+            // The name is read on the main thread from the characters typed during the frame
+            while let Some(character) = get_char_pressed() {
+                if !character.is_control() && high_score.name.chars().count() < MAX_NAME_LENGTH {
+                    high_score.name.push(character);
                 }
+            }
 
-                self.graphics_manager.draw_player_car(&self.player_car);
+            if is_key_pressed(KeyCode::Backspace) {
+                high_score.name.pop();
+            }
 
-                if let Some(collision_position) = self.game_over_collision {
-                    self.graphics_manager.draw_collision(collision_position.0, collision_position.1);
+            if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+                let mut high_score = high_score.clone();
+                if high_score.name.trim().is_empty() {
+                    high_score.name = DEFAULT_PLAYER_NAME.to_string();
                 }
 
-                self.graphics_manager.draw_game_over(self.score, self.session_record);
+                self.high_scores.insert(high_score);
+                if let Err(e) = self.high_scores.save() {
+                    error!("Error saving high scores: {}", e);
+                }
 
-                if game_action == GameAction::PauseResume {
-                    self.game_state = GameState::NotStarted;
+                self.pending_high_score = None;
+            }
+        }
+    }
 
-                    self.sounds_manager.stop_sound(SoundType::GameOver);
+    fn update_settings_menu(&mut self) {
+        if let Some(settings_menu) = self.settings_menu.as_mut() {
+            let mut key_bindings = self.config.keys.get_key_bindings();
+            let was_waiting_for_key = settings_menu.is_waiting_for_key();
 
-                    let mut current_speed = self.speed.lock().map_err(|e| RustyLock(LockError {
-                        message: format!("Impossible to lock the access to the current score: {}", e),
-                    }))?;
+            let mut asset_pack = self.asset_pack.clone();
+            let change = settings_menu.update(&mut key_bindings, &mut asset_pack);
 
-                    *current_speed = START_GAME_SPEED;
-                    self.graphics_manager.background.set_speed(*current_speed);
+            // The new key is not an action, even if it is already bound to one
+            if was_waiting_for_key && !settings_menu.is_waiting_for_key() {
+                self.input_queue.clear();
+            }
 
-                    self.bot_manager.bot_car_list.clear();
+            match change {
+                SettingsChange::KeyBindings => {
+                    // The new bindings are used immediately and kept for the next sessions
+                    self.input_queue.set_key_bindings(key_bindings.clone());
+                    self.config.keys = KeysConfig::from_key_bindings(&key_bindings);
+                    self.graphics_manager.set_key_bindings(key_bindings);
+                    self.save_config();
                 }
+                SettingsChange::AssetPack => {
+                    self.config.assets.pack = asset_pack.clone();
+                    self.requested_asset_pack = Some(asset_pack);
+                    self.save_config();
+                }
+                SettingsChange::None => {}
             }
         }
+    }
 
-        Ok(false)
+    fn get_step_action(&mut self, player_action: GameAction) -> GameAction {
+        let mut game_action = player_action;
+
+        if let Some(replay_player) = self.replay_player.as_mut() {
+            match self.simulation.get_state() {
+                // The run of the replay starts immediately
+                GameState::NotStarted => game_action = GameAction::PauseResume,
+                // The mute has already been applied, it does not change the run
+                // A replay of a run quit from the pause ends in the pause, the player takes over
+                GameState::Pause if replay_player.is_finished() => {
+                    info!("Replay finished in the pause, the game goes on with the keyboard");
+                    self.replay_player = None;
+                }
+                GameState::Running | GameState::Pause => {
+                    game_action = replay_player.get_action(self.simulation.get_run_step());
+                }
+                GameState::GameOver => {}
+            }
+        }
+
+        game_action
     }
 
-    fn stop(&mut self) {
-        self.speed_timer.stop();
+    fn record_action(&mut self, game_action: GameAction) {
+        let in_run = matches!(self.simulation.get_state(), GameState::Running | GameState::Pause);
 
-        if self.session_record < self.score {
-            self.session_record = self.score;
+        if let Some(replay_recorder) = self.replay_recorder.as_mut() {
+            // Only the actions changing the run are recorded, quitting ends the replay
+            if in_run && !matches!(game_action, GameAction::None | GameAction::Mute | GameAction::Quit) {
+                replay_recorder.record(self.simulation.get_run_step(), game_action);
+            }
         }
     }
 
-    fn move_player_car(&mut self, game_action: GameAction) {
-        // Get the new way if the player car can move
-        if let Some(new_way) = Game::get_destination_way(game_action, self.player_car.get_way()) {
-            self.player_car.set_way(new_way);
+    fn on_run_started(&mut self) {
+        info!("Run started with the seed {}", self.simulation.get_seed());
+        self.run_start_timestamp = Game::get_timestamp();
+
+        // A replay is not recorded again
+        if self.replay_player.is_none() {
+            self.replay_recorder = Some(Replay::new(self.simulation.get_seed(), self.simulation.get_config_hash()));
         }
     }
 
-    fn get_game_action(&self) -> RustyResult<GameAction> {
-        let receiver_input = self.receiver_input.lock().map_err(|e| RustyLock(LockError {
-            message: format!("Impossible to lock the access to the player car: {}", e),
-        }))?;
+    fn on_run_finished(&mut self) {
+        let score = self.simulation.get_score();
+        self.save_replay(Some(score));
 
-        match receiver_input.try_recv() {
-            Ok(key) => Ok(key),
-            Err(e) => {
-                if e == std::sync::mpsc::TryRecvError::Empty {
-                    Ok(GameAction::None)
-                } else {
-                    Err(RustyError::Recv(e))
+        // The replay is over, the game goes on with the keyboard
+        let is_replay = self.replay_player.is_some();
+        if let Some(replay_player) = self.replay_player.take() {
+            match replay_player.get_replay().final_score {
+                Some(final_score) if final_score != score => {
+                    warn!("Replay finished with a score of {} instead of {}", score, final_score);
                 }
+                _ => info!("Replay finished with a score of {}", score),
             }
         }
+
+        // Only the runs played by the player can enter the high score table
+        if !is_replay && self.high_scores.is_high_score(score) {
+            // The letters typed during the run are forgotten
+            while get_char_pressed().is_some() {}
+
+            self.pending_high_score = Some(HighScore {
+                name: String::new(),
+                score,
+                date: Game::get_timestamp(),
+                max_speed: self.simulation.get_max_speed(),
+                duration: self.simulation.get_game_time(),
+                seed: self.simulation.get_seed(),
+            });
+        }
     }
 
-    fn speed_up(timer_data: &mut TimerData) {
-        let TimerData::GameSpeed { speed: game_speed } = timer_data;
-        let game_speed_lock = game_speed.lock();
+    // The replay of the current run is written in the replay directory, only the last ones are kept
+    fn save_replay(&mut self, final_score: Option<u32>) {
+        if let Some(mut replay) = self.replay_recorder.take() {
+            replay.final_score = final_score;
+
+            let replay_directory = Replay::get_directory();
+            let replay_path = replay_directory.join(Replay::get_file_name(self.run_start_timestamp, replay.seed));
 
-        match game_speed_lock {
-            Ok(mut speed) => {
-                *speed += SPEED_INCREASE_VALUE;
+            match replay.save(&replay_path) {
+                Ok(()) => info!("Run recorded in {}", replay_path.display()),
+                Err(e) => error!("Error saving replay: {}", e),
             }
-            Err(e) => {
-                println!("Error lock current speed: {}", e);
+            if let Err(e) = Replay::remove_old_replays(&replay_directory, MAX_REPLAY_COUNT) {
+                warn!("Error removing the old replays: {}", e);
             }
-        };
+        }
     }
 
-    fn get_destination_way(game_action: GameAction, way: Way) -> Option<Way> {
-        let mut destination_way: Option<Way> = None;
-        for &(k, w1, w2) in &PLAYER_INPUT_AND_CAR_REACTION {
-            if k == game_action && w1 == way {
-                destination_way = Some(w2);
-                break;
-            }
+    fn get_timestamp() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+    }
+
+    fn on_state_changed(&mut self, previous: GameState, current: GameState) {
+        match (previous, current) {
+            (GameState::NotStarted, GameState::Running) => self.on_run_started(),
+            (_, GameState::GameOver) => self.on_run_finished(),
+            _ => {}
+        }
+
+        // Each state has its own music, the music of the previous state is stopped
+        // before playing the new one
+        if let Some((sound_type, _)) = Game::get_state_sound(previous) {
+            self.sounds_manager.stop_sound(sound_type);
+        }
+
+        if let Some((sound_type, play_loop)) = Game::get_state_sound(current) {
+            self.sounds_manager.play_sound(sound_type, play_loop);
+        }
+
+        if current == GameState::NotStarted {
+            self.graphics_manager.background.set_speed(self.simulation.get_speed());
         }
-        return destination_way;
     }
 
-    async fn manage_bot_cars(&mut self, delta_time: f32) -> RustyResult<Option<(Way, f32)>> {
-        self.bot_manager.spawn_car().await?;
+    fn get_state_sound(game_state: GameState) -> Option<(SoundType, bool)> {
+        match game_state {
+            GameState::NotStarted => Some((SoundType::Menu, true)),
+            GameState::Running => Some((SoundType::Game, true)),
+            // No sound in pause menu
+            GameState::Pause => None,
+            GameState::GameOver => Some((SoundType::GameOver, false)),
+        }
+    }
+
+    fn draw(&mut self, delta_time: f32, interpolation: f32) {
+        // Here we need to pay attention to the sequence of draw functions due to the
+        // superposition of the elements
+
+        match self.simulation.get_state() {
+            GameState::NotStarted => {
+                self.graphics_manager.background.move_texture(delta_time, self.simulation.get_lane_positions());
 
-        let mut is_colliding: Option<(Way, f32)> = None;
-        for bot_car in self.bot_manager.bot_car_list.iter_mut() {
-            bot_car.update_position(delta_time);
-            is_colliding = bot_car.is_colliding(&self.player_car);
-            if is_colliding.is_some() {
-                break;
+                if let Some(settings_menu) = &self.settings_menu {
+                    self.graphics_manager.draw_settings(settings_menu, self.asset_pack.as_deref());
+                } else if self.show_high_scores {
+                    self.graphics_manager.draw_high_scores(self.high_scores.get_entries());
+                } else {
+                    self.graphics_manager.draw_new_game();
+                }
             }
-        }
+            GameState::Running => {
+                // The background is moved with a speed of 80% of the current speed
+                self.graphics_manager.background.set_speed(self.simulation.get_speed() * 0.8);
+                self.graphics_manager.background.move_texture(delta_time, self.simulation.get_lane_positions());
 
-        self.bot_manager.bot_car_list.retain(|bot_car| {
-            !bot_car.is_out_of_screen()
-        });
+                for bot_car in self.simulation.get_bot_cars() {
+                    self.graphics_manager.draw_bot_car(bot_car, self.simulation.get_lane_positions(), interpolation);
+                }
+
+                self.graphics_manager.draw_player_car(self.simulation.get_player_car(), interpolation);
+                if self.show_hitboxes {
+                    self.graphics_manager.draw_hitboxes(&self.simulation, interpolation);
+                }
+                self.graphics_manager.draw_score(self.simulation.get_score());
+
+                if let Some(remaining_time) = self.simulation.get_remaining_time() {
+                    self.graphics_manager.draw_remaining_time(remaining_time);
+                }
+                if let Some(beat_phase) = self.simulation.get_beat_phase() {
+                    self.graphics_manager.draw_beat(beat_phase, self.simulation.get_on_beat_count());
+                }
+                if let Some(message) = self.simulation.get_level_message() {
+                    self.graphics_manager.draw_level_message(message);
+                }
+            }
+            GameState::Pause => {
+                // Draw game situation during the pause
 
-        Ok(is_colliding)
+                self.graphics_manager.background.draw(self.simulation.get_lane_positions());
+                self.graphics_manager.draw_score(self.simulation.get_score());
+
+                for bot_car in self.simulation.get_bot_cars() {
+                    // The cars are stopped, they are drawn at their last position
+                    self.graphics_manager.draw_bot_car(bot_car, self.simulation.get_lane_positions(), 1.0);
+                }
+
+                self.graphics_manager.draw_player_car(self.simulation.get_player_car(), 1.0);
+                if self.show_hitboxes {
+                    self.graphics_manager.draw_hitboxes(&self.simulation, 1.0);
+                }
+                self.graphics_manager.draw_pause(self.simulation.get_session_record());
+            }
+            GameState::GameOver => {
+                // Draw game situation at the end of the game
+
+                self.graphics_manager.background.draw(self.simulation.get_lane_positions());
+
+                for bot_car in self.simulation.get_bot_cars() {
+                    // The cars are stopped, they are drawn at their last position
+                    self.graphics_manager.draw_bot_car(bot_car, self.simulation.get_lane_positions(), 1.0);
+                }
+
+                self.graphics_manager.draw_player_car(self.simulation.get_player_car(), 1.0);
+                if self.show_hitboxes {
+                    self.graphics_manager.draw_hitboxes(&self.simulation, 1.0);
+                }
+
+                if let Some(collision_position) = self.simulation.get_game_over_collision() {
+                    self.graphics_manager.draw_collision(collision_position.0, collision_position.1);
+                }
+
+                match &self.pending_high_score {
+                    Some(high_score) => {
+                        self.graphics_manager.draw_name_entry(high_score.score, &high_score.name);
+                    }
+                    None => {
+                        self.graphics_manager.draw_game_over(self.simulation.get_score(),
+                                                             self.simulation.get_session_record(),
+                                                             self.simulation.get_seed(),
+                                                             self.simulation.is_level_complete());
+                    }
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,39 @@
+use std::hash::Hasher;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BEATS_PER_BAR, ON_BEAT_BONUS, ON_BEAT_TOLERANCE, TIME_ATTACK_DURATION};
+use crate::utils::stable_hasher::StableHasher;
+
+/// Rule deciding when a run ends
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum GameMode {
+    // The run goes on until the player hits a car
+    #[default]
+    Endless,
+    // The run also ends after TIME_ATTACK_DURATION seconds, the best distance wins
+    TimeAttack,
+    // The cars come on the beats of the game music, a lane change on a beat gives a bonus
+    Rhythm,
+}
+
+impl GameMode {
+    pub fn write_hash(&self, hasher: &mut StableHasher) {
+        match self {
+            GameMode::Endless => hasher.write_u8(0),
+            GameMode::TimeAttack => {
+                hasher.write_u8(1);
+                hasher.write_f32(TIME_ATTACK_DURATION);
+            }
+            GameMode::Rhythm => {
+                hasher.write_u8(2);
+                hasher.write_u64(BEATS_PER_BAR);
+                hasher.write_f32(ON_BEAT_TOLERANCE);
+                hasher.write_f32(ON_BEAT_BONUS);
+            }
+        }
+    }
+}
@@ -1,6 +1,13 @@
-use macroquad::prelude::{draw_texture, FileError, load_texture, screen_width, Texture2D, Vec2, WHITE};
+use macroquad::prelude::{Color, draw_rectangle, draw_texture, Texture2D, Vec2, WHITE};
 
-const ROAD_TEXTURE_PATH: &str = "assets/road.png";
+use crate::WINDOW_WIDTH;
+
+// The road texture has no lane marking, a dashed line is drawn between two lanes
+const LANE_MARKING_COLOR: Color = Color::new(0.77, 0.63, 0.29, 1.0);
+const LANE_MARKING_LENGTH: f32 = 80.0;
+const LANE_MARKING_WIDTH: f32 = 20.0;
+// Distance between the starts of two dashes, the texture width is a multiple of it to loop without jump
+const LANE_MARKING_INTERVAL: f32 = 160.0;
 
 #[derive(Clone)]
 pub struct Background {
@@ -10,31 +17,40 @@ pub struct Background {
 }
 
 impl Background {
-    pub async fn new() -> Result<Background, FileError> {
-        let background_texture = load_texture(ROAD_TEXTURE_PATH).await?;
-        Ok(Background {
-            texture: background_texture,
+    pub fn new(texture: Texture2D) -> Background {
+        Background {
+            texture,
             position: Vec2::new(0.0, 0.0),
             speed: 300.0,
-        })
+        }
     }
 
-    pub fn draw(&self) {
+    // The markings are drawn from the lanes of the configuration, they move with the road
+    pub fn draw(&self, lane_positions: &[f32]) {
         draw_texture(self.texture, self.position.x, self.position.y, WHITE);
-        draw_texture(self.texture, self.position.x + screen_width(), self.position.y, WHITE);
+        draw_texture(self.texture, self.position.x + WINDOW_WIDTH, self.position.y, WHITE);
+
+        for lanes in lane_positions.windows(2) {
+            let marking_y = (lanes[0] + lanes[1]) / 2.0 - LANE_MARKING_WIDTH / 2.0;
+            let mut marking_x = self.position.x;
+            while marking_x < WINDOW_WIDTH {
+                draw_rectangle(marking_x, marking_y, LANE_MARKING_LENGTH, LANE_MARKING_WIDTH, LANE_MARKING_COLOR);
+                marking_x += LANE_MARKING_INTERVAL;
+            }
+        }
     }
-    pub fn move_texture(&mut self, delta_time: f32) {
+    pub fn move_texture(&mut self, delta_time: f32, lane_positions: &[f32]) {
         // Update background position
         self.position.x -= self.speed * delta_time;
         // Create a continuous loop effect
-        if self.position.x < -screen_width() {
+        if self.position.x < -WINDOW_WIDTH {
             self.position.x = 0.0;
         }
 
-        self.draw();
+        self.draw(lane_positions);
     }
 
     pub fn set_speed(&mut self, speed: f32) {
         self.speed = speed;
     }
-}
\ No newline at end of file
+}
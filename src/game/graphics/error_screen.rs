@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use macroquad::input::{is_key_pressed, KeyCode};
+use macroquad::prelude::{BLACK, clear_background, draw_text, GRAY, next_frame, RED, WHITE};
+
+use crate::WINDOW_HEIGHT;
+use crate::game::graphics::graphics_manager::GraphicsManager;
+use crate::game::graphics::virtual_screen::VirtualScreen;
+use crate::utils::logger::get_log_file_path;
+use crate::utils::rusty_error::RustyError;
+
+const ERROR_TITLE_SIZE: f32 = 45.0;
+const ERROR_TEXT_SIZE: f32 = 22.0;
+const ERROR_LINE_HEIGHT: f32 = 26.0;
+const ERROR_TEXT_X_POSITION: f32 = 60.0;
+// Characters of a line of the error, the longer lines are wrapped to stay in the window
+const ERROR_LINE_LENGTH: usize = 100;
+const MAX_ERROR_LINES: usize = 18;
+
+// Shows the error which stopped the game until the player closes the window,
+// the assets may be the cause of the error so only the default font is used
+pub async fn show_error_screen(error: &RustyError, crash_log_path: Option<&Path>, fullscreen: bool) {
+    let virtual_screen = VirtualScreen::new(fullscreen);
+
+    let mut lines = get_wrapped_lines(&error.to_string());
+    if lines.len() > MAX_ERROR_LINES {
+        let hidden_lines = lines.len() - MAX_ERROR_LINES + 1;
+        lines.truncate(MAX_ERROR_LINES - 1);
+        lines.push(format!("... and {} more lines", hidden_lines));
+    }
+
+    let footer = match crash_log_path {
+        Some(path) => format!("The error has been saved in {}", path.display()),
+        None => "The error could not be saved in the crash log".to_string(),
+    };
+    let log_message = format!("Log of the session: {}", get_log_file_path().display());
+
+    while !is_key_pressed(KeyCode::Escape) && !is_key_pressed(KeyCode::Enter) && !is_key_pressed(KeyCode::KpEnter) {
+        virtual_screen.begin();
+        clear_background(BLACK);
+
+        GraphicsManager::draw_centered_text("The game has stopped because of an error", 80.0, ERROR_TITLE_SIZE, RED);
+
+        let mut y = 150.0;
+        for line in &lines {
+            draw_text(line, ERROR_TEXT_X_POSITION, y, ERROR_TEXT_SIZE, WHITE);
+            y += ERROR_LINE_HEIGHT;
+        }
+
+        GraphicsManager::draw_centered_text(&footer, WINDOW_HEIGHT - 100.0, ERROR_TEXT_SIZE, GRAY);
+        GraphicsManager::draw_centered_text(&log_message, WINDOW_HEIGHT - 70.0, ERROR_TEXT_SIZE, GRAY);
+        GraphicsManager::draw_centered_text("Press Escape or Enter to quit", WINDOW_HEIGHT - 35.0, ERROR_TEXT_SIZE, WHITE);
+
+        virtual_screen.end();
+
+        next_frame().await;
+    }
+}
+
+fn get_wrapped_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for line in text.lines() {
+        // The wrapped parts are indented more than the line, to be read as the same line
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let mut current = indent.to_string();
+
+        for word in line.split_whitespace() {
+            if current.len() > indent.len() && current.chars().count() + word.chars().count() >= ERROR_LINE_LENGTH {
+                lines.push(current);
+                current = format!("{}    ", indent);
+            }
+            if !current.ends_with(' ') && !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        lines.push(current);
+    }
+
+    lines
+}
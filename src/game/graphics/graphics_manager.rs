@@ -1,64 +1,158 @@
-use macroquad::prelude::{Color, draw_line, draw_text, draw_texture, load_texture, Texture2D, WHITE};
-use macroquad::shapes::{draw_rectangle, draw_rectangle_lines};
+use macroquad::prelude::{Color, draw_line, draw_text, draw_texture, draw_texture_ex, DrawTextureParams, FilterMode, GREEN, KeyCode, Texture2D, WHITE, YELLOW};
+use macroquad::shapes::{draw_circle, draw_rectangle, draw_rectangle_lines};
 use macroquad::text::measure_text;
 
 use crate::{GAME_NAME, WINDOW_HEIGHT, WINDOW_WIDTH};
-use crate::config::KEY_GAME;
-use crate::game::car::{Car, PLAYER_CAR_HEIGHT, Way};
+use crate::game::assets::asset_manager::{AssetManager, BOT_CAR_TEXTURES, COLLISION_TEXTURE, PLAYER_CAR_TEXTURE, ROAD_TEXTURE, TextureHandle};
+use crate::game::car::{Car, PLAYER_CAR_X_POSITION, Way};
 use crate::game::car::bot_car::BotCar;
+use crate::game::car::hitbox::{CarHitboxes, Hitbox};
 use crate::game::car::player_car::PlayerCar;
 use crate::game::graphics::background::Background;
-use crate::keyboard::{GameAction, get_action_description_from_game_action, get_key_code_from_game_action, get_str_from_key_code};
+use crate::game::high_scores::HighScore;
+use crate::game::settings_menu::SettingsMenu;
+use crate::game::simulation::Simulation;
+use crate::keyboard::{GAME_ACTION, GameAction, get_action_description_from_game_action, get_key_code_from_game_action, get_key_codes_from_game_action, get_str_from_key_code};
 use crate::utils::rusty_error::RustyResult;
 
-const FIRST_LANE_POSITION: f32 = WINDOW_HEIGHT * (220.0 / 720.0);
-const SECOND_LANE_POSITION: f32 = WINDOW_HEIGHT / 2.0;
-const THIRD_LANE_POSITION: f32 = WINDOW_HEIGHT * (500.0 / 720.0);
-
 const RUSTY_CORKS_TEXT_SIZE: f32 = 60.0;
 const ENTER_TEXT_SIZE: f32 = 35.0;
+const SEED_TEXT_SIZE: f32 = 25.0;
+const HIGH_SCORE_TEXT_SIZE: f32 = 24.0;
+
+const CONTROLS_RECTANGLE_HEIGHT: f32 = 320.0;
+const HIGH_SCORE_RECTANGLE_WIDTH: f32 = 1000.0;
+const HIGH_SCORE_RECTANGLE_HEIGHT: f32 = 600.0;
+
+// Circle beating with the music in the rhythm mode, it is the largest on the beat
+const BEAT_CIRCLE_RADIUS: f32 = 18.0;
+const BEAT_CIRCLE_PULSE: f32 = 10.0;
+
+// Title and x position of each column of the high score table
+const HIGH_SCORE_COLUMNS: [(&str, f32); 7] = [
+    ("#", 160.0),
+    ("Name", 195.0),
+    ("Score", 395.0),
+    ("Max speed", 500.0),
+    ("Duration", 630.0),
+    ("Date", 750.0),
+    ("Seed", 895.0),
+];
 
 const PLAY_MESSAGE: &str = "Ready to play ? Press ";
+const KEY_SETTINGS_HELP_MESSAGE: &str = "Up/Down: select   Enter: add a key   Backspace: remove a key   R: reset the keys";
+const ASSET_PACK_SETTINGS_HELP_MESSAGE: &str = "Up/Down: select   Left/Right: change the asset pack   R: reset the keys";
 
 
 const COLLISION_SIZE: f32 = 50.0;
-const COLLISION_TEXTURE_PATH: &str = "assets/collision.png";
+// Color of the opaque pixels of the hitboxes in the debug overlay
+const HITBOX_MASK_COLOR: [u8; 4] = [255, 0, 0, 110];
 
 #[derive(Clone)]
 pub struct GraphicsManager {
+    assets: AssetManager,
     pub background: Background,
-    pub collision: Texture2D,
+    collision: TextureHandle,
+    player_car: TextureHandle,
+    // The bot cars only know the index of their texture in this list
+    bot_cars: Vec<TextureHandle>,
+    // Keys displayed in the menus
+    key_bindings: Vec<(KeyCode, GameAction)>,
+    // Opaque pixels of the hitboxes drawn by the debug overlay, none for an empty hitbox
+    player_car_mask: Option<Texture2D>,
+    bot_car_masks: Vec<Option<Texture2D>>,
 }
 
 impl GraphicsManager {
-    pub async fn new() -> RustyResult<GraphicsManager> {
-        let background = Background::new().await?;
-        let collision = load_texture(COLLISION_TEXTURE_PATH).await?;
+    pub fn new(assets: AssetManager, key_bindings: Vec<(KeyCode, GameAction)>) -> RustyResult<GraphicsManager> {
+        let road = assets.get_texture_handle(ROAD_TEXTURE)?;
+        let collision = assets.get_texture_handle(COLLISION_TEXTURE)?;
+        let player_car = assets.get_texture_handle(PLAYER_CAR_TEXTURE)?;
+        let bot_cars = assets.get_texture_handles(BOT_CAR_TEXTURES)?;
+
         Ok(GraphicsManager {
-            background,
+            background: Background::new(assets.get_texture(road)),
+            assets,
             collision,
+            player_car,
+            bot_cars,
+            key_bindings,
+            player_car_mask: None,
+            bot_car_masks: Vec::new(),
         })
     }
 
-    fn draw_depending_way(&self, texture: Texture2D, way: &Way, x: f32, height_subtraction: f32) {
-        match way {
-            Way::Upper => {
-                draw_texture(texture, x, FIRST_LANE_POSITION - height_subtraction / 2.0, WHITE);
-            }
-            Way::Center => {
-                draw_texture(texture, x, SECOND_LANE_POSITION - height_subtraction / 2.0, WHITE);
-            }
-            Way::Lower => {
-                draw_texture(texture, x, THIRD_LANE_POSITION - height_subtraction / 2.0, WHITE);
+    // The textures of the previous assets are deleted
+    pub fn set_assets(&mut self, assets: AssetManager) -> RustyResult<()> {
+        let road = assets.get_texture_handle(ROAD_TEXTURE)?;
+        self.collision = assets.get_texture_handle(COLLISION_TEXTURE)?;
+        self.player_car = assets.get_texture_handle(PLAYER_CAR_TEXTURE)?;
+        self.bot_cars = assets.get_texture_handles(BOT_CAR_TEXTURES)?;
+        self.background.texture = assets.get_texture(road);
+
+        self.assets.delete();
+        self.assets = assets;
+        Ok(())
+    }
+
+    // The textures of the overlay are created once, the previous ones are deleted
+    pub fn set_hitboxes(&mut self, hitboxes: &CarHitboxes) {
+        for mask in self.bot_car_masks.iter().chain([&self.player_car_mask]).flatten() {
+            mask.delete();
+        }
+
+        self.player_car_mask = GraphicsManager::create_hitbox_mask(&hitboxes.player_car);
+        self.bot_car_masks = hitboxes.bot_cars.iter().map(GraphicsManager::create_hitbox_mask).collect();
+    }
+
+    fn create_hitbox_mask(hitbox: &Hitbox) -> Option<Texture2D> {
+        let (_, _, width, height) = hitbox.get_rectangle();
+        let (width, height) = (width as u32, height as u32);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let mut bytes = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                bytes.extend_from_slice(if hitbox.is_opaque(x, y) { &HITBOX_MASK_COLOR } else { &[0; 4] });
             }
         }
+
+        let texture = Texture2D::from_rgba8(width as u16, height as u16, &bytes);
+        texture.set_filter(FilterMode::Nearest);
+        Some(texture)
+    }
+
+    pub fn set_key_bindings(&mut self, key_bindings: Vec<(KeyCode, GameAction)>) {
+        self.key_bindings = key_bindings;
+    }
+
+    // The texture is centered on the lane of the way
+    fn draw_depending_way(&self, texture: TextureHandle, lane_positions: &[f32], way: Way, x: f32) {
+        let texture: Texture2D = self.assets.get_texture(texture);
+        let Way(lane) = way;
+        draw_texture(texture, x, lane_positions[lane] - texture.height() / 2.0, WHITE);
     }
-    pub fn draw_bot_car(&self, bot_car: &BotCar) {
-        self.draw_depending_way(bot_car.get_texture(), &bot_car.get_way(), bot_car.x_position, PLAYER_CAR_HEIGHT);
+    pub fn draw_bot_car(&self, bot_car: &BotCar, lane_positions: &[f32], interpolation: f32) {
+        self.draw_depending_way(self.bot_cars[bot_car.get_texture_id()],
+                                lane_positions,
+                                bot_car.get_way(),
+                                bot_car.get_interpolated_x_position(interpolation));
     }
 
-    pub fn draw_player_car(&self, player_car: &PlayerCar) {
-        self.draw_depending_way(player_car.get_texture(), &player_car.get_way(), WINDOW_WIDTH / 4.0, PLAYER_CAR_HEIGHT);
+    // The player car is not always on a lane, it is drawn at its own position with its tilt
+    pub fn draw_player_car(&self, player_car: &PlayerCar, interpolation: f32) {
+        let y_position = player_car.get_interpolated_y_position(interpolation);
+        let texture = self.assets.get_texture(self.player_car);
+        draw_texture_ex(texture,
+                        PLAYER_CAR_X_POSITION,
+                        y_position - texture.height() / 2.0,
+                        WHITE,
+                        DrawTextureParams {
+                            rotation: player_car.get_tilt(),
+                            ..Default::default()
+                        });
     }
 
     pub fn draw_score(&self, score: u32) {
@@ -66,10 +160,33 @@ impl GraphicsManager {
         draw_text(&score_text, 0.0, 60.0, 60.0, WHITE);
     }
 
-    pub fn draw_game_over(&self, score: u32, session_record: u32) {
+    pub fn draw_remaining_time(&self, remaining_time: f32) {
+        let time_text = format!("Time: {}", remaining_time.ceil() as u32);
+        let text_size = measure_text(&time_text, None, 60, 1.0);
+        draw_text(&time_text, WINDOW_WIDTH - text_size.width - 20.0, 60.0, 60.0, WHITE);
+    }
+
+    // `beat_phase` goes from 0 on a beat to 1 before the next one
+    pub fn draw_beat(&self, beat_phase: f32, on_beat_count: u32) {
+        let pulse = (1.0 - beat_phase).powi(2);
+        let center_x = WINDOW_WIDTH - BEAT_CIRCLE_RADIUS - BEAT_CIRCLE_PULSE - 20.0;
+        draw_circle(center_x, 40.0, BEAT_CIRCLE_RADIUS + BEAT_CIRCLE_PULSE * pulse, Color::new(1.0, 1.0, 0.0, 0.4 + 0.6 * pulse));
+
+        let on_beat_text = format!("On beat: {}", on_beat_count);
+        let text_size = measure_text(&on_beat_text, None, 40, 1.0);
+        draw_text(&on_beat_text, center_x - BEAT_CIRCLE_RADIUS - BEAT_CIRCLE_PULSE - text_size.width - 20.0, 52.0, 40.0, WHITE);
+    }
+
+    // Message of a level, over the road
+    pub fn draw_level_message(&self, message: &str) {
+        GraphicsManager::draw_centered_text(message, 140.0, RUSTY_CORKS_TEXT_SIZE, YELLOW);
+    }
+
+    pub fn draw_game_over(&self, score: u32, session_record: u32, seed: u64, level_complete: bool) {
         GraphicsManager::draw_menu_rectangle();
 
-        GraphicsManager::draw_centered_text("Game Over",
+        let title = if level_complete { "Level complete" } else { "Game Over" };
+        GraphicsManager::draw_centered_text(title,
                                             (WINDOW_HEIGHT / 2.0) - 50.0,
                                             RUSTY_CORKS_TEXT_SIZE,
                                             WHITE);
@@ -87,7 +204,13 @@ impl GraphicsManager {
                                             ENTER_TEXT_SIZE,
                                             WHITE);
 
-        GraphicsManager::draw_key_text(GameAction::PauseResume, 100.0);
+        let seed_text = format!("Seed: {}", seed);
+        GraphicsManager::draw_centered_text(seed_text.as_str(),
+                                            (WINDOW_HEIGHT / 2.0) + 65.0,
+                                            SEED_TEXT_SIZE,
+                                            WHITE);
+
+        self.draw_key_text(GameAction::PauseResume, 100.0);
     }
 
     pub fn draw_pause(&self, session_record: u32) {
@@ -104,7 +227,7 @@ impl GraphicsManager {
                                             ENTER_TEXT_SIZE,
                                             WHITE);
 
-        GraphicsManager::draw_key_text(GameAction::PauseResume, 100.0);
+        self.draw_key_text(GameAction::PauseResume, 100.0);
     }
 
     pub fn draw_new_game(&self) {
@@ -116,9 +239,9 @@ impl GraphicsManager {
                                             WHITE);
 
 
-        GraphicsManager::draw_key_text(GameAction::PauseResume, 150.0);
+        self.draw_key_text(GameAction::PauseResume, 220.0);
 
-        draw_rectangle_lines(WINDOW_WIDTH / 2.0 - (500.0 / 2.0), WINDOW_HEIGHT / 2.0 - (180.0 / 2.0), 500.0, 250.0, 5.0, Color::new(0.3, 0.3, 0.3, 0.8));
+        draw_rectangle_lines(WINDOW_WIDTH / 2.0 - (500.0 / 2.0), WINDOW_HEIGHT / 2.0 - (180.0 / 2.0), 500.0, CONTROLS_RECTANGLE_HEIGHT, 5.0, Color::new(0.3, 0.3, 0.3, 0.8));
 
         GraphicsManager::draw_centered_text("Controls",
                                             (WINDOW_HEIGHT / 2.0) - 67.0,
@@ -133,11 +256,163 @@ impl GraphicsManager {
                   3.0,
                   Color::new(1.0, 1.0, 1.0, 0.8));
 
-        GraphicsManager::draw_key_binds(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0 - (text_size.height / 2.0), ENTER_TEXT_SIZE, WHITE);
+        self.draw_key_binds(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0 - (text_size.height / 2.0), ENTER_TEXT_SIZE, WHITE);
     }
 
-    pub fn draw_collision(&self, way: Way, x: f32) {
-        self.draw_depending_way(self.collision, &way, x - COLLISION_SIZE / 2.0, COLLISION_SIZE);
+    pub fn draw_name_entry(&self, score: u32, name: &str) {
+        GraphicsManager::draw_menu_rectangle();
+
+        GraphicsManager::draw_centered_text("New high score !",
+                                            (WINDOW_HEIGHT / 2.0) - 50.0,
+                                            RUSTY_CORKS_TEXT_SIZE,
+                                            WHITE);
+
+        let score_text = format!("Score: {}", score);
+        GraphicsManager::draw_centered_text(score_text.as_str(),
+                                            (WINDOW_HEIGHT / 2.0) - 10.0,
+                                            ENTER_TEXT_SIZE,
+                                            WHITE);
+
+        // The underscore shows where the next letter will be written
+        let name_text = format!("Name: {}_", name);
+        GraphicsManager::draw_centered_text(name_text.as_str(),
+                                            (WINDOW_HEIGHT / 2.0) + 40.0,
+                                            ENTER_TEXT_SIZE,
+                                            WHITE);
+
+        GraphicsManager::draw_centered_text("Press Enter to save",
+                                            (WINDOW_HEIGHT / 2.0) + 100.0,
+                                            ENTER_TEXT_SIZE,
+                                            WHITE);
+    }
+
+    pub fn draw_high_scores(&self, high_scores: &[HighScore]) {
+        draw_rectangle((WINDOW_WIDTH / 2.0) - (HIGH_SCORE_RECTANGLE_WIDTH / 2.0),
+                       (WINDOW_HEIGHT / 2.0) - (HIGH_SCORE_RECTANGLE_HEIGHT / 2.0),
+                       HIGH_SCORE_RECTANGLE_WIDTH,
+                       HIGH_SCORE_RECTANGLE_HEIGHT,
+                       Color::new(0.5, 0.5, 0.5, 0.8));
+
+        GraphicsManager::draw_centered_text("High scores",
+                                            (WINDOW_HEIGHT / 2.0) - 220.0,
+                                            RUSTY_CORKS_TEXT_SIZE,
+                                            WHITE);
+
+        let mut y = (WINDOW_HEIGHT / 2.0) - 160.0;
+        for (title, x) in HIGH_SCORE_COLUMNS {
+            draw_text(title, x, y, HIGH_SCORE_TEXT_SIZE, WHITE);
+        }
+
+        if high_scores.is_empty() {
+            GraphicsManager::draw_centered_text("No high score yet",
+                                                WINDOW_HEIGHT / 2.0,
+                                                ENTER_TEXT_SIZE,
+                                                WHITE);
+        }
+
+        for (rank, high_score) in high_scores.iter().enumerate() {
+            y += HIGH_SCORE_TEXT_SIZE + 8.0;
+
+            let values = [
+                (rank + 1).to_string(),
+                high_score.name.clone(),
+                high_score.score.to_string(),
+                format!("{:.0}", high_score.max_speed),
+                high_score.get_formatted_duration(),
+                high_score.get_formatted_date(),
+                high_score.seed.to_string(),
+            ];
+            for (value, (_, x)) in values.iter().zip(HIGH_SCORE_COLUMNS) {
+                draw_text(value, x, y, HIGH_SCORE_TEXT_SIZE, WHITE);
+            }
+        }
+
+        self.draw_key_text_with_message("Back to the menu ? Press ", GameAction::HighScores, 260.0);
+    }
+
+    pub fn draw_settings(&self, settings_menu: &SettingsMenu, asset_pack: Option<&str>) {
+        draw_rectangle((WINDOW_WIDTH / 2.0) - (HIGH_SCORE_RECTANGLE_WIDTH / 2.0),
+                       (WINDOW_HEIGHT / 2.0) - (HIGH_SCORE_RECTANGLE_HEIGHT / 2.0),
+                       HIGH_SCORE_RECTANGLE_WIDTH,
+                       HIGH_SCORE_RECTANGLE_HEIGHT,
+                       Color::new(0.5, 0.5, 0.5, 0.8));
+
+        GraphicsManager::draw_centered_text("Settings",
+                                            (WINDOW_HEIGHT / 2.0) - 220.0,
+                                            RUSTY_CORKS_TEXT_SIZE,
+                                            WHITE);
+
+        let mut y = (WINDOW_HEIGHT / 2.0) - 160.0;
+        for &(action, description) in &GAME_ACTION {
+            let selected = Some(action) == settings_menu.get_selected_action();
+
+            let keys = if selected && settings_menu.is_waiting_for_key() {
+                "Press a new key (Escape to cancel)".to_string()
+            } else {
+                self.get_keys_text(action)
+            };
+
+            let color = if selected { YELLOW } else { WHITE };
+            draw_text(description, HIGH_SCORE_COLUMNS[1].1, y, ENTER_TEXT_SIZE, color);
+            draw_text(&keys, HIGH_SCORE_COLUMNS[4].1, y, ENTER_TEXT_SIZE, color);
+
+            y += ENTER_TEXT_SIZE + 5.0;
+        }
+
+        let pack_color = if settings_menu.get_selected_action().is_none() { YELLOW } else { WHITE };
+        draw_text("Asset pack: ", HIGH_SCORE_COLUMNS[1].1, y, ENTER_TEXT_SIZE, pack_color);
+        draw_text(&format!("< {} >", asset_pack.unwrap_or("Default")), HIGH_SCORE_COLUMNS[4].1, y, ENTER_TEXT_SIZE, pack_color);
+
+        if let Some(message) = settings_menu.get_message() {
+            GraphicsManager::draw_centered_text(message, (WINDOW_HEIGHT / 2.0) + 165.0, HIGH_SCORE_TEXT_SIZE, YELLOW);
+        }
+
+        let help_message = match settings_menu.get_selected_action() {
+            Some(_) => KEY_SETTINGS_HELP_MESSAGE,
+            None => ASSET_PACK_SETTINGS_HELP_MESSAGE,
+        };
+        GraphicsManager::draw_centered_text(help_message,
+                                            (WINDOW_HEIGHT / 2.0) + 210.0,
+                                            HIGH_SCORE_TEXT_SIZE,
+                                            WHITE);
+
+        self.draw_key_text_with_message("Back to the menu ? Press ", GameAction::Settings, 260.0);
+    }
+
+    pub fn draw_collision(&self, x: f32, y: f32) {
+        draw_texture(self.assets.get_texture(self.collision), x - COLLISION_SIZE / 2.0, y - COLLISION_SIZE / 2.0, WHITE);
+    }
+
+    // Debug overlay: the rectangle of each hitbox, and its opaque pixels with the pixel collision
+    pub fn draw_hitboxes(&self, simulation: &Simulation, interpolation: f32) {
+        let hitboxes = simulation.get_hitboxes();
+        let pixel_collision = simulation.is_pixel_collision();
+
+        let (_, player_car_height) = hitboxes.player_car.get_texture_size();
+        let player_car_y = simulation.get_player_car().get_interpolated_y_position(interpolation) - player_car_height / 2.0;
+        GraphicsManager::draw_hitbox(&hitboxes.player_car,
+                                     self.player_car_mask.filter(|_| pixel_collision),
+                                     PLAYER_CAR_X_POSITION,
+                                     player_car_y);
+
+        let lane_positions = simulation.get_lane_positions();
+        for bot_car in simulation.get_bot_cars() {
+            let texture_id = bot_car.get_texture_id();
+            let mask = self.bot_car_masks.get(texture_id).copied().flatten().filter(|_| pixel_collision);
+            GraphicsManager::draw_hitbox(&hitboxes.bot_cars[texture_id],
+                                         mask,
+                                         bot_car.get_interpolated_x_position(interpolation),
+                                         bot_car.get_y_position(lane_positions, hitboxes));
+        }
+    }
+
+    // `x` and `y` are the top left corner of the texture of the car
+    fn draw_hitbox(hitbox: &Hitbox, mask: Option<Texture2D>, x: f32, y: f32) {
+        let (hitbox_x, hitbox_y, width, height) = hitbox.get_rectangle();
+        if let Some(mask) = mask {
+            draw_texture(mask, x + hitbox_x, y + hitbox_y, WHITE);
+        }
+        draw_rectangle_lines(x + hitbox_x, y + hitbox_y, width, height, 2.0, GREEN);
     }
 
     fn draw_menu_rectangle() {
@@ -148,12 +423,16 @@ impl GraphicsManager {
                        Color::new(0.5, 0.5, 0.5, 0.5));
     }
 
-    fn draw_key_text(game_action: GameAction, y_offset: f32) {
+    fn draw_key_text(&self, game_action: GameAction, y_offset: f32) {
+        self.draw_key_text_with_message(PLAY_MESSAGE, game_action, y_offset);
+    }
+
+    fn draw_key_text_with_message(&self, message: &str, game_action: GameAction, y_offset: f32) {
         // Get the key for the game action
-        let key_for_new_game = get_key_code_from_game_action(game_action);
+        let key_for_new_game = get_key_code_from_game_action(&self.key_bindings, game_action);
         match key_for_new_game {
             Some(key) => {
-                let text_new_game = format!("{}{}", PLAY_MESSAGE, get_str_from_key_code(key));
+                let text_new_game = format!("{}{}", message, get_str_from_key_code(key));
                 GraphicsManager::draw_centered_text(text_new_game.as_str(),
                                                     (WINDOW_HEIGHT / 2.0) + y_offset,
                                                     ENTER_TEXT_SIZE,
@@ -166,20 +445,29 @@ impl GraphicsManager {
         }
     }
 
-    fn draw_centered_text(text: &str, y: f32, font_size: f32, color: Color) {
+    pub fn draw_centered_text(text: &str, y: f32, font_size: f32, color: Color) {
         // Measure text size to center it
         let text_size = measure_text(text, None, font_size as u16, 1.0);
         draw_text(text, (WINDOW_WIDTH / 2.0) - (text_size.width / 2.0), y, font_size, color);
     }
 
-    fn draw_key_binds(x: f32, y: f32, font_size: f32, color: Color) {
+    // All the keys of the action separated by commas
+    fn get_keys_text(&self, game_action: GameAction) -> String {
+        let mut key_names = Vec::new();
+        for key in get_key_codes_from_game_action(&self.key_bindings, game_action) {
+            key_names.push(get_str_from_key_code(key));
+        }
+        key_names.join(", ")
+    }
+
+    fn draw_key_binds(&self, x: f32, y: f32, font_size: f32, color: Color) {
         let mut y_offset = 0.0;
-        for key in KEY_GAME {
+        for &(action, _) in &GAME_ACTION {
             // Get the action description from the game action
-            let game_action = get_action_description_from_game_action(key.1);
+            let game_action = get_action_description_from_game_action(action);
             if let Some(game_action) = game_action {
-                // Format the game action with the key
-                let text = format!("{}{}", game_action, get_str_from_key_code(key.0));
+                // Format the game action with its keys
+                let text = format!("{}{}", game_action, self.get_keys_text(action));
                 let text_size = measure_text(&text, None, ENTER_TEXT_SIZE as u16, 1.0);
                 draw_text(&text, x - (text_size.width / 2.0), y - (text_size.height / 2.0) + y_offset, font_size, color);
 
@@ -1,3 +1,5 @@
 pub mod background;
+pub mod error_screen;
 
-pub mod graphics_manager;
\ No newline at end of file
+pub mod graphics_manager;
+pub mod virtual_screen;
\ No newline at end of file
@@ -0,0 +1,60 @@
+use macroquad::camera::{Camera2D, set_camera, set_default_camera};
+use macroquad::prelude::{BLACK, clear_background, draw_texture_ex, DrawTextureParams, FilterMode, render_target, RenderTarget, screen_height, screen_width, vec2, WHITE};
+use macroquad::window::set_fullscreen;
+
+use crate::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// Screen of WINDOW_WIDTH x WINDOW_HEIGHT where the game is drawn, whatever the size of the window.
+/// It is scaled to fill the window without changing its proportions, with black bars around it
+pub struct VirtualScreen {
+    render_target: RenderTarget,
+    fullscreen: bool,
+}
+
+impl VirtualScreen {
+    pub fn new(fullscreen: bool) -> VirtualScreen {
+        let render_target = render_target(WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32);
+        render_target.texture.set_filter(FilterMode::Linear);
+
+        VirtualScreen {
+            render_target,
+            fullscreen,
+        }
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        self.fullscreen = !self.fullscreen;
+        set_fullscreen(self.fullscreen);
+    }
+
+    // The next drawings go to the virtual screen, in its coordinates
+    pub fn begin(&self) {
+        // The y axis of a render target goes up, the zoom is positive to keep the origin at the top left
+        set_camera(&Camera2D {
+            zoom: vec2(2.0 / WINDOW_WIDTH, 2.0 / WINDOW_HEIGHT),
+            target: vec2(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0),
+            render_target: Some(self.render_target),
+            ..Default::default()
+        });
+    }
+
+    // Draws the virtual screen on the window
+    pub fn end(&self) {
+        set_default_camera();
+        clear_background(BLACK);
+
+        // The biggest size with the proportions of the virtual screen, centered in the window
+        let scale = (screen_width() / WINDOW_WIDTH).min(screen_height() / WINDOW_HEIGHT);
+        let width = WINDOW_WIDTH * scale;
+        let height = WINDOW_HEIGHT * scale;
+
+        draw_texture_ex(self.render_target.texture,
+                        ((screen_width() - width) / 2.0).floor(),
+                        ((screen_height() - height) / 2.0).floor(),
+                        WHITE,
+                        DrawTextureParams {
+                            dest_size: Some(vec2(width, height)),
+                            ..Default::default()
+                        });
+    }
+}
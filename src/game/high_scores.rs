@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::HIGH_SCORE_COUNT;
+use crate::utils::data_directory::get_data_directory;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+const HIGH_SCORE_FILE_NAME: &str = "high_scores.txt";
+
+// The first line of the file gives its version, a line per high score follows:
+// score, max speed, duration, seed, date and name separated by tabulations
+const HIGH_SCORE_FILE_HEADER: &str = "rusty_corks_high_scores";
+const HIGH_SCORE_FILE_VERSION: u32 = 1;
+
+pub const MAX_NAME_LENGTH: usize = 12;
+
+#[derive(Clone, PartialEq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    // Seconds since 1970-01-01
+    pub date: u64,
+    pub max_speed: f32,
+    // Duration of the run in seconds of game
+    pub duration: f32,
+    pub seed: u64,
+}
+
+impl HighScore {
+    pub fn get_formatted_date(&self) -> String {
+        // Conversion of the number of days since 1970-01-01 to a civil date
+        // (algorithm "civil_from_days" of Howard Hinnant)
+        let days = (self.date / 86400) as i64 + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+
+    pub fn get_formatted_duration(&self) -> String {
+        let seconds = self.duration as u32;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Best scores of every session, kept sorted from the best to the worst
+pub struct HighScoreTable {
+    path: PathBuf,
+    entries: Vec<HighScore>,
+}
+
+impl HighScoreTable {
+    pub fn new() -> HighScoreTable {
+        HighScoreTable {
+            path: get_data_directory().join(HIGH_SCORE_FILE_NAME),
+            entries: Vec::new(),
+        }
+    }
+
+    // Loads the high scores of the user, a missing file is an empty table
+    pub fn load() -> RustyResult<HighScoreTable> {
+        let path = get_data_directory().join(HIGH_SCORE_FILE_NAME);
+
+        let entries = if path.is_file() {
+            let content = fs::read_to_string(&path).map_err(RustyError::file("read the high scores", &path))?;
+            HighScoreTable::parse(&path, &content)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(HighScoreTable {
+            path,
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> RustyResult<()> {
+        let mut content = format!("{} {}\n", HIGH_SCORE_FILE_HEADER, HIGH_SCORE_FILE_VERSION);
+        for entry in &self.entries {
+            content.push_str(&format!("{}\t{}\t{}\t{}\t{}\t{}\n",
+                                      entry.score,
+                                      entry.max_speed,
+                                      entry.duration,
+                                      entry.seed,
+                                      entry.date,
+                                      entry.name));
+        }
+
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory).map_err(RustyError::file("create the high score directory", directory))?;
+        }
+
+        fs::write(&self.path, content).map_err(RustyError::file("write the high scores", &self.path))
+    }
+
+    pub fn get_entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    // A score enters the table if the table is not full or if it beats the last one
+    pub fn is_high_score(&self, score: u32) -> bool {
+        score > 0 && (self.entries.len() < HIGH_SCORE_COUNT
+            || self.entries.last().is_some_and(|last| score > last.score))
+    }
+
+    pub fn insert(&mut self, mut high_score: HighScore) {
+        // The tabulations are the separator of the file
+        high_score.name = high_score.name.replace(['\t', '\n', '\r'], " ");
+
+        // For the same score, the oldest one stays in front
+        let position = self.entries.iter()
+            .position(|entry| entry.score < high_score.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(position, high_score);
+        self.entries.truncate(HIGH_SCORE_COUNT);
+    }
+
+    fn parse(path: &Path, content: &str) -> RustyResult<Vec<HighScore>> {
+        let invalid_line = |line_number: usize, reason: &str| RustyError::HighScore(
+            format!("{} line {}: {}", path.display(), line_number + 1, reason)
+        );
+
+        let mut lines = content.lines().enumerate();
+
+        let expected_header = format!("{} {}", HIGH_SCORE_FILE_HEADER, HIGH_SCORE_FILE_VERSION);
+        match lines.next() {
+            Some((_, header)) if header.trim() == expected_header => {}
+            _ => return Err(invalid_line(0, &format!("expected \"{}\"", expected_header))),
+        }
+
+        let mut entries = Vec::new();
+        for (line_number, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.splitn(6, '\t').collect();
+            if fields.len() != 6 {
+                return Err(invalid_line(line_number, "expected 6 fields"));
+            }
+
+            let invalid_field = |name: &str| invalid_line(line_number, &format!("invalid {}", name));
+            entries.push(HighScore {
+                score: fields[0].parse().map_err(|_| invalid_field("score"))?,
+                max_speed: fields[1].parse().map_err(|_| invalid_field("max speed"))?,
+                duration: fields[2].parse().map_err(|_| invalid_field("duration"))?,
+                seed: fields[3].parse().map_err(|_| invalid_field("seed"))?,
+                date: fields[4].parse().map_err(|_| invalid_field("date"))?,
+                name: fields[5].to_string(),
+            });
+        }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        entries.truncate(HIGH_SCORE_COUNT);
+
+        Ok(entries)
+    }
+}
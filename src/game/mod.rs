@@ -1,4 +1,14 @@
+#[allow(clippy::module_inception)]
 pub mod game;
+pub mod assets;
 pub mod car;
+pub mod benchmark;
+pub mod difficulty;
+pub mod game_mode;
 pub mod graphics;
+pub mod high_scores;
+pub mod replay;
+pub mod rhythm;
+pub mod settings_menu;
+pub mod simulation;
 pub mod sounds;
\ No newline at end of file
@@ -0,0 +1,293 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::REPLAY_DIRECTORY;
+use crate::keyboard::GameAction;
+use crate::utils::data_directory::get_data_directory;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+// Replay file layout (little endian):
+// magic, version, seed (u64), config hash (u64), final score flag (u8) and score (u32),
+// action count (u32), then for each action its step (u32) and its code (u8)
+const REPLAY_MAGIC: &[u8; 4] = b"RCRP";
+const REPLAY_VERSION: u8 = 1;
+const REPLAY_HEADER_SIZE: usize = 4 + 1 + 8 + 8 + 1 + 4 + 4;
+const REPLAY_ACTION_SIZE: usize = 4 + 1;
+const REPLAY_EXTENSION: &str = "rcr";
+
+const GAME_ACTION_CODE: [(GameAction, u8); 7] = [
+    (GameAction::Up, 0),
+    (GameAction::Down, 1),
+    (GameAction::Mute, 2),
+    (GameAction::PauseResume, 3),
+    (GameAction::Quit, 4),
+    (GameAction::HighScores, 5),
+    (GameAction::Settings, 6),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct ReplayAction {
+    // Number of simulation steps since the start of the run
+    pub step: u32,
+    pub action: GameAction,
+}
+
+/// Everything needed to play a run again: the simulation is deterministic, so the same seed
+/// and the same actions at the same steps give exactly the same run
+#[derive(Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub config_hash: u64,
+    // None when the run was quit from the pause
+    pub final_score: Option<u32>,
+    pub actions: Vec<ReplayAction>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config_hash: u64) -> Replay {
+        Replay {
+            seed,
+            config_hash,
+            final_score: None,
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, step: u32, action: GameAction) {
+        self.actions.push(ReplayAction {
+            step,
+            action,
+        });
+    }
+
+    // Directory of the recorded runs, in the user data directory
+    pub fn get_directory() -> PathBuf {
+        get_data_directory().join(REPLAY_DIRECTORY)
+    }
+
+    // The replays are named from the start of their run, "<timestamp>-<seed>.rcr"
+    pub fn get_file_name(timestamp: u64, seed: u64) -> String {
+        format!("{}-{}.{}", timestamp, seed, REPLAY_EXTENSION)
+    }
+
+    // Removes the oldest replays of the directory to keep `max_count` replays, the other files are not touched
+    pub fn remove_old_replays(directory: &Path, max_count: usize) -> RustyResult<()> {
+        let entries = fs::read_dir(directory).map_err(RustyError::file("read the replay directory", directory))?;
+
+        // The timestamp of the name gives the age of the replay
+        let mut replays: Vec<(u64, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == REPLAY_EXTENSION))
+            .filter_map(|path| {
+                let timestamp = path.file_stem()?.to_str()?.split('-').next()?.parse().ok()?;
+                Some((timestamp, path))
+            })
+            .collect();
+        if replays.len() <= max_count {
+            return Ok(());
+        }
+
+        replays.sort();
+        for (_, path) in &replays[..replays.len() - max_count] {
+            fs::remove_file(path).map_err(RustyError::file("remove the old replay", path))?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> RustyResult<()> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(REPLAY_HEADER_SIZE + self.actions.len() * REPLAY_ACTION_SIZE);
+
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.config_hash.to_le_bytes());
+        bytes.push(self.final_score.is_some() as u8);
+        bytes.extend_from_slice(&self.final_score.unwrap_or(0).to_le_bytes());
+        bytes.extend_from_slice(&(self.actions.len() as u32).to_le_bytes());
+
+        for replay_action in &self.actions {
+            bytes.extend_from_slice(&replay_action.step.to_le_bytes());
+            bytes.push(Replay::get_code_from_game_action(replay_action.action));
+        }
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(RustyError::file("create the replay directory", directory))?;
+        }
+
+        fs::write(path, bytes).map_err(RustyError::file("write the replay", path))
+    }
+
+    pub fn load(path: &Path) -> RustyResult<Replay> {
+        let bytes = fs::read(path).map_err(RustyError::file("read the replay", path))?;
+
+        let invalid_file = |reason: &str| RustyError::Replay(
+            format!("{} is not a valid replay: {}", path.display(), reason)
+        );
+
+        if bytes.len() < REPLAY_HEADER_SIZE || &bytes[0..4] != REPLAY_MAGIC {
+            return Err(invalid_file("unknown format"));
+        }
+        if bytes[4] != REPLAY_VERSION {
+            return Err(invalid_file(&format!("unsupported version {}", bytes[4])));
+        }
+
+        let seed = u64::from_le_bytes(Replay::read_array(&bytes, 5));
+        let config_hash = u64::from_le_bytes(Replay::read_array(&bytes, 13));
+        let final_score = match bytes[21] {
+            0 => None,
+            _ => Some(u32::from_le_bytes(Replay::read_array(&bytes, 22))),
+        };
+        let action_count = u32::from_le_bytes(Replay::read_array(&bytes, 26)) as usize;
+
+        if bytes.len() != REPLAY_HEADER_SIZE + action_count * REPLAY_ACTION_SIZE {
+            return Err(invalid_file("truncated actions"));
+        }
+
+        let mut actions = Vec::with_capacity(action_count);
+        for index in 0..action_count {
+            let offset = REPLAY_HEADER_SIZE + index * REPLAY_ACTION_SIZE;
+            let step = u32::from_le_bytes(Replay::read_array(&bytes, offset));
+            let action = Replay::get_game_action_from_code(bytes[offset + 4])
+                .ok_or_else(|| invalid_file(&format!("unknown action {}", bytes[offset + 4])))?;
+
+            actions.push(ReplayAction {
+                step,
+                action,
+            });
+        }
+
+        Ok(Replay {
+            seed,
+            config_hash,
+            final_score,
+            actions,
+        })
+    }
+
+    fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+        let mut array = [0; N];
+        array.copy_from_slice(&bytes[offset..offset + N]);
+        array
+    }
+
+    fn get_code_from_game_action(game_action: GameAction) -> u8 {
+        let mut code = u8::MAX;
+        for &(action, action_code) in &GAME_ACTION_CODE {
+            if action == game_action {
+                code = action_code;
+            }
+        }
+        code
+    }
+
+    fn get_game_action_from_code(code: u8) -> Option<GameAction> {
+        let mut game_action = None;
+        for &(action, action_code) in &GAME_ACTION_CODE {
+            if action_code == code {
+                game_action = Some(action);
+            }
+        }
+        game_action
+    }
+}
+
+/// Gives back the recorded actions of a replay at the step they were received
+pub struct ReplayPlayer {
+    replay: Replay,
+    next_action: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        ReplayPlayer {
+            replay,
+            next_action: 0,
+        }
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    // Every action of the replay has been played
+    pub fn is_finished(&self) -> bool {
+        self.next_action >= self.replay.actions.len()
+    }
+
+    pub fn get_action(&mut self, step: u32) -> GameAction {
+        match self.replay.actions.get(self.next_action) {
+            Some(replay_action) if replay_action.step <= step => {
+                self.next_action += 1;
+                replay_action.action
+            }
+            _ => GameAction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn save_and_load_give_the_same_replay() {
+        let directory = env::temp_dir().join("rusty_corks_replay_test");
+        let path = directory.join(Replay::get_file_name(1700000000, 42));
+
+        let mut replay = Replay::new(42, 1234);
+        replay.record(10, GameAction::PauseResume);
+        replay.record(250, GameAction::Up);
+        replay.final_score = Some(5000);
+        replay.save(&path).unwrap();
+
+        let loaded = Replay::load(&path).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.config_hash, replay.config_hash);
+        assert_eq!(loaded.final_score, replay.final_score);
+        assert!(loaded.actions == replay.actions);
+    }
+
+    #[test]
+    fn only_the_last_replays_are_kept() {
+        let directory = env::temp_dir().join("rusty_corks_replay_rotation_test");
+        fs::create_dir_all(&directory).unwrap();
+
+        // The timestamps have different lengths, the oldest replay is not the first name
+        let timestamps = [999999999, 1700000002, 1700000000, 1700000001];
+        for (seed, &timestamp) in timestamps.iter().enumerate() {
+            Replay::new(seed as u64, 0).save(&directory.join(Replay::get_file_name(timestamp, seed as u64))).unwrap();
+        }
+        fs::write(directory.join("notes.txt"), "kept").unwrap();
+
+        Replay::remove_old_replays(&directory, 2).unwrap();
+
+        let mut file_names: Vec<String> = fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        file_names.sort();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(file_names, ["1700000001-3.rcr", "1700000002-1.rcr", "notes.txt"]);
+    }
+
+    #[test]
+    fn player_gives_the_actions_at_their_step() {
+        let mut replay = Replay::new(42, 1234);
+        replay.record(10, GameAction::Up);
+        replay.record(12, GameAction::PauseResume);
+        let mut replay_player = ReplayPlayer::new(replay);
+
+        assert_eq!(replay_player.get_action(9), GameAction::None);
+        assert_eq!(replay_player.get_action(10), GameAction::Up);
+        assert_eq!(replay_player.get_action(11), GameAction::None);
+        assert!(!replay_player.is_finished());
+        assert_eq!(replay_player.get_action(12), GameAction::PauseResume);
+        assert!(replay_player.is_finished());
+        assert_eq!(replay_player.get_action(13), GameAction::None);
+    }
+}
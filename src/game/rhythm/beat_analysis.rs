@@ -0,0 +1,343 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::BEATS_PER_BAR;
+use crate::game::rhythm::beat_map::{BeatMap, Section};
+use crate::game::sounds::audio_file;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+// Duration of a frame of the loudness curve, in seconds
+const FRAME_DURATION: f64 = 0.01;
+// Tempos searched by the analysis, a faster or a slower music is found at twice or half its tempo
+const MIN_DETECTED_BPM: f64 = 60.0;
+const MAX_DETECTED_BPM: f64 = 200.0;
+// Between two tempos matching the music as well, the closest to this one is chosen
+const PREFERRED_BPM: f64 = 120.0;
+// A beat is moved to the attack of a sound closer than this part of a beat
+const BEAT_SNAP: f64 = 0.1;
+// A bar louder or quieter than this difference of intensity starts a new section
+const SECTION_CHANGE: f64 = 0.2;
+// A shorter music does not have enough beats to find its tempo, in seconds
+const MIN_DURATION: f64 = 2.0;
+// A music whose loudest frame is quieter than this level (from 0 to 1) is silent
+const SILENCE_LEVEL: f64 = 0.0001;
+
+// Analyzes a music file and writes its beat map, next to the music without `output`.
+// Returns the beat map and the path of its file
+pub fn write_beat_map(music_path: &Path, output: Option<&Path>) -> RustyResult<(BeatMap, PathBuf)> {
+    let beat_map = analyze_file(music_path)?;
+
+    let output = output.map_or_else(|| PathBuf::from(BeatMap::get_path(&music_path.to_string_lossy())), Path::to_path_buf);
+    beat_map.save(&output)?;
+
+    Ok((beat_map, output))
+}
+
+// Beat map of a music file, it is not written
+pub fn analyze_file(music_path: &Path) -> RustyResult<BeatMap> {
+    let bytes = fs::read(music_path).map_err(RustyError::file("read the music", music_path))?;
+    let analysis_error = |e: String| RustyError::BeatMap(format!("{}: {}", music_path.display(), e));
+
+    let (samples, sample_rate) = audio_file::decode(&bytes).map_err(analysis_error)?;
+    analyze(&samples, sample_rate).map_err(analysis_error)
+}
+
+// The tempo comes from the repetitions of the attacks of the sounds (the rises of the loudness),
+// the beats are placed at this tempo on the strongest attacks and the sections follow the loudness of the bars
+pub fn analyze(samples: &[f32], sample_rate: u32) -> Result<BeatMap, String> {
+    let duration = samples.len() as f64 / sample_rate as f64;
+    if duration < MIN_DURATION {
+        return Err(format!("the music must last at least {} seconds", MIN_DURATION));
+    }
+
+    let frame_length = ((sample_rate as f64 * FRAME_DURATION) as usize).max(1);
+    let frame_duration = frame_length as f64 / sample_rate as f64;
+    let levels: Vec<f64> = samples.chunks(frame_length).map(get_level).collect();
+    if levels.iter().all(|&level| level < SILENCE_LEVEL) {
+        return Err("the music is silent".to_string());
+    }
+
+    // The loudness is compressed like by the ear, a quiet sound has an attack too.
+    // The music starts after a silence, a sound at its start is an attack
+    let loudness: Vec<f64> = levels.iter().map(|level| (1.0 + 1000.0 * level).ln()).collect();
+    let mut attacks = vec![loudness[0]];
+    attacks.extend(loudness.windows(2).map(|frames| (frames[1] - frames[0]).max(0.0)));
+
+    let beat_period = get_beat_period(&attacks)?;
+    let first_beat = get_first_beat(&attacks, beat_period);
+    let round = |value: f64| (value * 1000.0).round() / 1000.0;
+
+    let duration = round(duration);
+    // An attack is somewhere in its frame, the beat is put in the middle
+    let beats: Vec<f64> = get_beat_frames(&attacks, beat_period, first_beat).into_iter()
+        .map(|frame| round((frame + 0.5) * frame_duration))
+        .filter(|&time| time < duration)
+        .collect();
+    if beats.is_empty() {
+        return Err("no beat found in the music".to_string());
+    }
+
+    let sections = get_sections(samples, sample_rate, &beats, duration);
+
+    Ok(BeatMap {
+        bpm: (600.0 / (beat_period * frame_duration)).round() / 10.0,
+        duration,
+        beats,
+        sections,
+    })
+}
+
+// Root mean square of the samples
+fn get_level(samples: &[f32]) -> f64 {
+    (samples.iter().map(|&sample| (sample as f64).powi(2)).sum::<f64>() / samples.len().max(1) as f64).sqrt()
+}
+
+// Number of frames between two beats, it is not a whole number
+fn get_beat_period(attacks: &[f64]) -> Result<f64, String> {
+    let mean = attacks.iter().sum::<f64>() / attacks.len() as f64;
+    let centered: Vec<f64> = attacks.iter().map(|attack| attack - mean).collect();
+    // Similarity of the attacks with themselves `lag` frames later
+    let autocorrelation = |lag: usize| -> f64 {
+        if lag >= centered.len() {
+            return 0.0;
+        }
+        let count = centered.len() - lag;
+        (0..count).map(|frame| centered[frame] * centered[frame + lag]).sum::<f64>() / count as f64
+    };
+
+    let min_lag = (60.0 / MAX_DETECTED_BPM / FRAME_DURATION).floor() as usize;
+    let max_lag = (60.0 / MIN_DETECTED_BPM / FRAME_DURATION).ceil() as usize;
+
+    // A period between two frames splits its repetition between them, the closest neighbour is added
+    let get_repetition = |lag: usize| autocorrelation(lag) + autocorrelation(lag - 1).max(autocorrelation(lag + 1));
+    // A beat repeats 2 beats later too. The attacks also repeat at twice and half the tempo,
+    // the tempos far from the preferred one are less likely
+    let get_score = |lag: usize| {
+        let bpm = 60.0 / (lag as f64 * FRAME_DURATION);
+        let weight = (-0.5 * (bpm / PREFERRED_BPM).log2().powi(2)).exp();
+        (get_repetition(lag) + get_repetition(2 * lag)) * weight
+    };
+    let best_lag = (min_lag..=max_lag)
+        .max_by(|&first, &second| get_score(first).total_cmp(&get_score(second)))
+        .unwrap_or(min_lag);
+    if autocorrelation(best_lag) <= 0.0 {
+        return Err("no beat found in the music".to_string());
+    }
+
+    // The repetition several beats later gives a more precise period, the error is divided by the number of beats
+    let mut period = best_lag as f64;
+    for beat_count in [2, 4, 8] {
+        let lag = (period * beat_count as f64).round() as usize;
+        if lag + beat_count >= centered.len() / 2 {
+            break;
+        }
+
+        let peak = (lag - beat_count..=lag + beat_count)
+            .max_by(|&first, &second| autocorrelation(first).total_cmp(&autocorrelation(second)))
+            .unwrap_or(lag);
+
+        // The top of the parabola going through the peak and its neighbours
+        let (before, at, after) = (autocorrelation(peak - 1), autocorrelation(peak), autocorrelation(peak + 1));
+        let curvature = before - 2.0 * at + after;
+        let shift = if curvature < 0.0 { (0.5 * (before - after) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+        period = (peak as f64 + shift) / beat_count as f64;
+    }
+
+    Ok(period)
+}
+
+// Frame of the first beat, the beats at this phase are on the strongest attacks
+fn get_first_beat(attacks: &[f64], beat_period: f64) -> f64 {
+    // Tenths of frames are tried
+    let phase_count = (beat_period * 10.0) as usize;
+    (0..phase_count)
+        .map(|phase| phase as f64 / 10.0)
+        .max_by(|&first, &second| get_phase_strength(attacks, beat_period, first).total_cmp(&get_phase_strength(attacks, beat_period, second)))
+        .unwrap_or(0.0)
+}
+
+fn get_phase_strength(attacks: &[f64], beat_period: f64, phase: f64) -> f64 {
+    let mut strength = 0.0;
+    let mut frame = phase;
+    while frame < (attacks.len() - 1) as f64 {
+        // Linear interpolation between the frames
+        let index = frame as usize;
+        let fraction = frame - index as f64;
+        strength += attacks[index] * (1.0 - fraction) + attacks[index + 1] * fraction;
+        frame += beat_period;
+    }
+    strength
+}
+
+// Frames of the beats: each beat of the tempo is moved to a strong attack close to it.
+// The beats start at the first strong attack, an introduction without rhythm has no beat
+fn get_beat_frames(attacks: &[f64], beat_period: f64, first_beat: f64) -> Vec<f64> {
+    let mean = attacks.iter().sum::<f64>() / attacks.len() as f64;
+    let deviation = (attacks.iter().map(|attack| (attack - mean).powi(2)).sum::<f64>() / attacks.len() as f64).sqrt();
+    let strong_attack = mean + deviation;
+    let snap_distance = BEAT_SNAP * beat_period;
+
+    // The phase is found in the first beat period, a beat a bit before it can be at the very start of the music
+    let mut frame = if first_beat - beat_period >= -snap_distance { first_beat - beat_period } else { first_beat };
+    let mut beats = Vec::new();
+    while frame < attacks.len() as f64 {
+        let first = (frame - snap_distance).ceil().max(0.0) as usize;
+        let last = ((frame + snap_distance).floor() as usize).min(attacks.len() - 1);
+        let strongest = (first..=last).max_by(|&first, &second| attacks[first].total_cmp(&attacks[second]));
+
+        match strongest {
+            Some(strongest) if attacks[strongest] > strong_attack => beats.push(strongest as f64),
+            _ if !beats.is_empty() => beats.push(frame),
+            _ => {}
+        }
+        frame += beat_period;
+    }
+    beats
+}
+
+// The bars with close loudness are grouped in sections
+fn get_sections(samples: &[f32], sample_rate: u32, beats: &[f64], duration: f64) -> Vec<Section> {
+    // The beats after the last whole bar are a part of it, a short bar would not have the loudness of the music
+    let mut bar_starts: Vec<f64> = beats.chunks(BEATS_PER_BAR as usize)
+        .filter(|bar| bar.len() == BEATS_PER_BAR as usize)
+        .map(|bar| bar[0])
+        .collect();
+    if bar_starts.is_empty() {
+        bar_starts.push(beats[0]);
+    }
+
+    let bars: Vec<(f64, f64, f64)> = bar_starts.iter().enumerate()
+        .map(|(index, &start)| {
+            let end = bar_starts.get(index + 1).copied().unwrap_or(duration);
+            let first_sample = ((start * sample_rate as f64) as usize).min(samples.len());
+            let last_sample = ((end * sample_rate as f64) as usize).min(samples.len());
+            // The first section also covers the start of the music before the first beat
+            let start = if index == 0 { 0.0 } else { start };
+            (start, end, get_level(&samples[first_sample..last_sample]))
+        })
+        .collect();
+    let max_level = bars.iter().map(|&(_, _, level)| level).fold(f64::EPSILON, f64::max);
+
+    // Each section keeps the intensities of its bars, a bar far from the first one starts a new section
+    let mut sections: Vec<(f64, f64, Vec<f64>)> = Vec::new();
+    for (start, end, level) in bars {
+        let intensity = level / max_level;
+        match sections.last_mut() {
+            Some((_, section_end, intensities)) if (intensity - intensities[0]).abs() <= SECTION_CHANGE => {
+                *section_end = end;
+                intensities.push(intensity);
+            }
+            _ => sections.push((start, end, vec![intensity])),
+        }
+    }
+
+    // The loudest section has the intensity 1, a music without quiet part is one section of intensity 1
+    let sections: Vec<(f64, f64, f64)> = sections.into_iter()
+        .map(|(start, end, intensities)| (start, end, intensities.iter().sum::<f64>() / intensities.len() as f64))
+        .collect();
+    let max_intensity = sections.iter().map(|&(_, _, intensity)| intensity).fold(f64::EPSILON, f64::max);
+
+    sections.into_iter()
+        .map(|(start, end, intensity)| Section {
+            start,
+            end,
+            intensity: (intensity / max_intensity * 100.0).round() / 100.0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 22050;
+
+    // Music with a click on each beat: a short 1 kHz tone fading out, over a light noise.
+    // `get_volume` gives the volume of the click at its time
+    fn get_click_track(bpm: f64, offset: f64, duration: f64, get_volume: impl Fn(f64) -> f64) -> Vec<f32> {
+        let sample_count = (duration * SAMPLE_RATE as f64) as usize;
+        let beat_duration = 60.0 / bpm;
+        // The noise is the same for every run of the test
+        let mut noise_state: u32 = 1;
+
+        (0..sample_count)
+            .map(|index| {
+                let time = index as f64 / SAMPLE_RATE as f64;
+                noise_state = noise_state.wrapping_mul(1664525).wrapping_add(1013904223);
+                let noise = (noise_state >> 8) as f64 / (1 << 24) as f64 - 0.5;
+
+                let mut sample = 0.01 * noise;
+                if time >= offset {
+                    let beat_time = offset + ((time - offset) / beat_duration).floor() * beat_duration;
+                    let click_time = time - beat_time;
+                    if click_time < 0.03 {
+                        sample += get_volume(beat_time) * (-click_time / 0.008).exp() * (2.0 * PI * 1000.0 * click_time).sin();
+                    }
+                }
+                sample as f32
+            })
+            .collect()
+    }
+
+    fn check_click_track(bpm: f64, offset: f64) {
+        let samples = get_click_track(bpm, offset, 12.0, |_| 0.8);
+        let beat_map = analyze(&samples, SAMPLE_RATE).unwrap();
+
+        assert!((beat_map.bpm - bpm).abs() <= 0.5, "{} BPM detected for {} BPM", beat_map.bpm, bpm);
+        assert!((beat_map.beats[0] - offset).abs() <= 0.015, "first beat at {} instead of {}", beat_map.beats[0], offset);
+        assert_eq!(beat_map.duration, 12.0);
+
+        // Every beat is on a click
+        let beat_duration = 60.0 / bpm;
+        let expected_count = ((12.0 - offset) / beat_duration).ceil() as usize;
+        assert!(beat_map.beats.len().abs_diff(expected_count) <= 1);
+        for (index, &time) in beat_map.beats.iter().enumerate() {
+            assert!((time - (offset + index as f64 * beat_duration)).abs() <= 0.015, "beat {} at {}", index, time);
+        }
+
+        // A constant music is one section
+        assert_eq!(beat_map.sections.len(), 1);
+        assert_eq!(beat_map.sections[0].intensity, 1.0);
+    }
+
+    #[test]
+    fn click_track_at_90_bpm() {
+        check_click_track(90.0, 0.3);
+    }
+
+    #[test]
+    fn click_track_at_120_bpm() {
+        check_click_track(120.0, 0.0);
+    }
+
+    #[test]
+    fn click_track_at_150_bpm() {
+        check_click_track(150.0, 0.55);
+    }
+
+    #[test]
+    fn quiet_and_loud_parts_are_sections() {
+        let samples = get_click_track(120.0, 0.0, 16.0, |time| if time < 8.0 { 0.1 } else { 0.8 });
+        let beat_map = analyze(&samples, SAMPLE_RATE).unwrap();
+
+        assert_eq!(beat_map.bpm, 120.0);
+        assert_eq!(beat_map.sections.len(), 2);
+        assert!((beat_map.sections[0].end - 8.0).abs() <= 0.015);
+        assert!(beat_map.get_intensity(4.0) < 0.3);
+        assert_eq!(beat_map.get_intensity(12.0), 1.0);
+    }
+
+    #[test]
+    fn silent_music_has_no_beat() {
+        let samples = vec![0.0; 5 * SAMPLE_RATE as usize];
+        assert_eq!(analyze(&samples, SAMPLE_RATE), Err("the music is silent".to_string()));
+    }
+
+    #[test]
+    fn short_music_is_refused() {
+        let samples = get_click_track(120.0, 0.0, 1.0, |_| 0.8);
+        assert_eq!(analyze(&samples, SAMPLE_RATE), Err("the music must last at least 2 seconds".to_string()));
+    }
+}
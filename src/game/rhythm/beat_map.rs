@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+// "musics/track.ogg" has its beat map in "musics/track.beats.toml"
+const BEAT_MAP_EXTENSION: &str = "beats.toml";
+
+const BEAT_MAP_FILE_HEADER: &str = "\
+# Rusty Corks beat map, written by `rusty_corks analyze`
+# The times are in seconds from the start of the music, the intensity goes from 0 (quietest part) to 1 (loudest part)
+# The rhythm mode reads this file when the tempo of the music is not declared, it can be edited by hand
+
+";
+
+/// Tempo, beats and loudness of a music, detected by the analysis of the music and saved next to it
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BeatMap {
+    pub bpm: f64,
+    // Duration of the music, the beats start again when it loops
+    pub duration: f64,
+    pub beats: Vec<f64>,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+}
+
+/// Part of a music with the same loudness
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Section {
+    pub start: f64,
+    pub end: f64,
+    pub intensity: f64,
+}
+
+impl BeatMap {
+    pub fn get_path(music_path: &str) -> String {
+        Path::new(music_path).with_extension(BEAT_MAP_EXTENSION).to_string_lossy().to_string()
+    }
+
+    // Every problem of the file is reported at once
+    pub fn parse(content: &str) -> Result<BeatMap, String> {
+        // The TOML errors give the line and the column of the problem
+        let beat_map: BeatMap = toml::from_str(content).map_err(|e| e.to_string())?;
+
+        let problems = beat_map.validate();
+        if !problems.is_empty() {
+            return Err(problems.join(", "));
+        }
+
+        Ok(beat_map)
+    }
+
+    pub fn save(&self, path: &Path) -> RustyResult<()> {
+        let content = toml::to_string(self).map_err(|e| RustyError::BeatMap(
+            format!("Impossible to write {}: {}", path.display(), e)
+        ))?;
+
+        fs::write(path, format!("{}{}", BEAT_MAP_FILE_HEADER, content)).map_err(RustyError::file("write the beat map", path))
+    }
+
+    // The parts of the music without section are as loud as possible
+    pub fn get_intensity(&self, time: f64) -> f64 {
+        self.sections.iter()
+            .find(|section| section.start <= time && time < section.end)
+            .map_or(1.0, |section| section.intensity)
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !(self.bpm.is_finite() && self.bpm > 0.0) {
+            problems.push("bpm must be positive".to_string());
+        }
+        if !(self.duration.is_finite() && self.duration > 0.0) {
+            problems.push("duration must be positive".to_string());
+        }
+
+        if self.beats.is_empty() {
+            problems.push("beats is empty".to_string());
+        }
+        if self.beats.iter().any(|&time| !(time.is_finite() && (0.0..self.duration).contains(&time))) {
+            problems.push("the beats must be between 0 and the duration of the music".to_string());
+        }
+        if self.beats.windows(2).any(|beats| beats[0] >= beats[1]) {
+            problems.push("the beats must be in the order of their time".to_string());
+        }
+
+        for (index, section) in self.sections.iter().enumerate() {
+            if !(0.0 <= section.start && section.start < section.end && section.end <= self.duration) {
+                problems.push(format!("the section {} must end after its start, during the music", index + 1));
+            }
+            if !(0.0..=1.0).contains(&section.intensity) {
+                problems.push(format!("the intensity of the section {} must be between 0 and 1", index + 1));
+            }
+        }
+        if self.sections.windows(2).any(|sections| sections[0].end > sections[1].start) {
+            problems.push("the sections must be in the order of their time, without overlap".to_string());
+        }
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEAT_MAP: &str = "\
+bpm = 120.0
+duration = 4.0
+beats = [0.25, 0.75, 1.25, 1.75, 2.25, 2.75, 3.25, 3.75]
+
+[[sections]]
+start = 0.0
+end = 2.0
+intensity = 0.2
+
+[[sections]]
+start = 2.0
+end = 4.0
+intensity = 1.0
+";
+
+    #[test]
+    fn beat_map_is_parsed() {
+        let beat_map = BeatMap::parse(BEAT_MAP).unwrap();
+        assert_eq!(beat_map.bpm, 120.0);
+        assert_eq!(beat_map.beats.len(), 8);
+        assert_eq!(beat_map.get_intensity(1.0), 0.2);
+        assert_eq!(beat_map.get_intensity(3.0), 1.0);
+
+        // The saved content gives the same beat map
+        assert_eq!(BeatMap::parse(&toml::to_string(&beat_map).unwrap()), Ok(beat_map));
+    }
+
+    #[test]
+    fn parts_without_section_are_loud() {
+        let beat_map = BeatMap::parse("bpm = 120.0\nduration = 4.0\nbeats = [0.5]\n").unwrap();
+        assert_eq!(beat_map.get_intensity(1.0), 1.0);
+    }
+
+    #[test]
+    fn invalid_beat_maps_are_refused() {
+        let check = |content: &str, problem: &str| {
+            let result = BeatMap::parse(content);
+            assert!(result.as_ref().is_err_and(|e| e.contains(problem)), "{:?} instead of {}", result, problem);
+        };
+
+        check("bpm = 0.0\nduration = 4.0\nbeats = [0.5]\n", "bpm must be positive");
+        check("bpm = nan\nduration = 4.0\nbeats = [0.5]\n", "bpm must be positive");
+        check("bpm = inf\nduration = 4.0\nbeats = [0.5]\n", "bpm must be positive");
+        check("bpm = 120.0\nduration = inf\nbeats = [0.5]\n", "duration must be positive");
+        check("bpm = 120.0\nduration = 4.0\nbeats = [0.5, nan]\n", "the beats must be between 0 and the duration of the music");
+        check("bpm = 120.0\nduration = 4.0\nbeats = []\n", "beats is empty");
+        check("bpm = 120.0\nduration = 4.0\nbeats = [0.5, 4.5]\n", "the beats must be between 0 and the duration of the music");
+        check("bpm = 120.0\nduration = 4.0\nbeats = [1.0, 0.5]\n", "the beats must be in the order of their time");
+        check(&BEAT_MAP.replace("intensity = 0.2", "intensity = 1.5"), "the intensity of the section 1 must be between 0 and 1");
+        check(&BEAT_MAP.replace("end = 2.0", "end = 3.0"), "the sections must be in the order of their time, without overlap");
+        check(&BEAT_MAP.replace("end = 4.0", "end = 5.0"), "the section 2 must end after its start, during the music");
+        // The TOML errors give their line
+        check(&BEAT_MAP.replace("bpm = 120.0", "bpm = \"fast\""), "line 1");
+        check(&BEAT_MAP.replace("intensity = 0.2", "loudness = 0.2"), "unknown field `loudness`");
+    }
+}
@@ -0,0 +1,546 @@
+use std::fs;
+use std::hash::Hasher;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use toml::Spanned;
+
+use crate::config::{BEATS_PER_BAR, LEVEL_MESSAGE_DURATION, QUIET_INTENSITY, START_GAME_SPEED};
+use crate::game::car::Way;
+use crate::game::rhythm::beat_analysis;
+use crate::game::rhythm::beat_map::BeatMap;
+use crate::game::rhythm::Tempo;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+use crate::utils::stable_hasher::StableHasher;
+
+// "musics/track.ogg" has its level in "musics/track.level.toml"
+const LEVEL_EXTENSION: &str = "level.toml";
+
+const LEVEL_FILE_HEADER: &str = "\
+# Rusty Corks level, play it with `rusty_corks --level <file>`
+# Each car, speed and event happens at its beat (from 0, the first beat of the music, 0.5 is half a beat)
+# or at its time in seconds from the start of the music. The lanes go from 1 (top lane), the cars from 1
+# (first texture of the bot cars, a random car without it). The events are `message` (with a `text`) and `end`
+
+";
+
+/// Hand-made level: the cars, the speeds and the events of a run, placed on the beats of its music.
+/// The entries keep their place in the file, the problems are reported with their line
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    // Music of the level, from the directory of the level file, the game music without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub music: Option<String>,
+    // Tempo of the music, the beats of the entries follow it
+    pub bpm: f64,
+    // Time of the first beat from the start of the music, in seconds
+    #[serde(default)]
+    pub offset: f64,
+    // Speed at the start of the level, in pixels per second
+    pub speed: f64,
+    #[serde(default)]
+    pub cars: Vec<Spanned<LevelCar>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub speeds: Vec<Spanned<LevelSpeed>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<Spanned<LevelEvent>>,
+}
+
+/// Car entering the road at the right of the screen
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelCar {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beat: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<f64>,
+    pub lane: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub car: Option<usize>,
+}
+
+/// Speed of the cars from this moment
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelSpeed {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beat: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<f64>,
+    pub speed: f64,
+}
+
+/// Moment of the level changing the run or the screen
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelEvent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beat: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<f64>,
+    #[serde(rename = "type")]
+    pub event_type: LevelEventType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LevelEventType {
+    // The text is shown for LEVEL_MESSAGE_DURATION seconds
+    Message,
+    // The level is complete, the run ends
+    End,
+}
+
+/// Car of a level ready to be spawned, the indexes start from 0
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PlannedCar {
+    pub time: f32,
+    pub way: Way,
+    pub texture_id: Option<usize>,
+}
+
+/// Level played by the simulation, the times are in seconds from the start of the music
+/// and the entries are in the order of their time
+#[derive(Clone, PartialEq, Debug)]
+pub struct LevelTrack {
+    cars: Vec<PlannedCar>,
+    start_speed: f32,
+    // Time and speed of each speed change
+    speeds: Vec<(f32, f32)>,
+    messages: Vec<(f32, String)>,
+    end_time: Option<f32>,
+}
+
+impl Level {
+    pub fn get_path(music_path: &str) -> String {
+        Path::new(music_path).with_extension(LEVEL_EXTENSION).to_string_lossy().to_string()
+    }
+
+    // The music of the level is found from the directory of the level file
+    pub fn load(path: &Path, lane_count: usize, bot_car_count: usize) -> RustyResult<Level> {
+        let content = fs::read_to_string(path).map_err(RustyError::file("read the level", path))?;
+
+        let mut level = Level::parse(&content, lane_count, bot_car_count).map_err(|problems| RustyError::Level(
+            format!("{}:\n  - {}", path.display(), problems.join("\n  - "))
+        ))?;
+
+        if let Some(music) = &level.music {
+            let directory = path.parent().unwrap_or(Path::new(""));
+            level.music = Some(directory.join(music).to_string_lossy().to_string());
+        }
+
+        Ok(level)
+    }
+
+    // Every problem of the file is reported at once, with its line. The lanes and the cars are checked
+    // with the road and the bot cars of the game
+    pub fn parse(content: &str, lane_count: usize, bot_car_count: usize) -> Result<Level, Vec<String>> {
+        // The TOML errors give the line and the column of the problem
+        let level: Level = toml::from_str(content).map_err(|e| vec![e.to_string()])?;
+
+        let problems = level.validate(content, lane_count, bot_car_count);
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        Ok(level)
+    }
+
+    pub fn save(&self, path: &Path) -> RustyResult<()> {
+        let content = toml::to_string(self).map_err(|e| RustyError::Level(
+            format!("Impossible to write {}: {}", path.display(), e)
+        ))?;
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(RustyError::file("create the level directory", directory))?;
+        }
+
+        fs::write(path, format!("{}{}", LEVEL_FILE_HEADER, content)).map_err(RustyError::file("write the level", path))
+    }
+
+    // First level of a music, the author completes it by hand: a car on each beat of the loud parts,
+    // on any lane but the free lane of the bar. The free lane can move to a next lane at the start of a bar,
+    // this beat has no car to let the player move too. The level ends at the end of the music
+    pub fn from_beat_map(beat_map: &BeatMap, music: Option<String>, lane_count: usize) -> RustyResult<Level> {
+        // A road of one lane has no free lane
+        if lane_count < 2 {
+            return Err(RustyError::Level(format!("a level needs a road of 2 lanes or more, not {}", lane_count)));
+        }
+
+        let beat_duration = 60.0 / beat_map.bpm;
+
+        // The same music always gives the same level
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut free_lane = rng.gen_range(1..=lane_count);
+        let mut cars = Vec::new();
+        for &time in &beat_map.beats {
+            let beat = ((time - beat_map.beats[0]) / beat_duration).round();
+            if beat > 0.0 && (beat as u64).is_multiple_of(BEATS_PER_BAR) {
+                let next_free_lane = (free_lane as i64 + rng.gen_range(-1..=1)).clamp(1, lane_count as i64) as usize;
+                if next_free_lane != free_lane {
+                    free_lane = next_free_lane;
+                    continue;
+                }
+            }
+            if beat_map.get_intensity(time) < QUIET_INTENSITY as f64 {
+                continue;
+            }
+
+            let mut lane = rng.gen_range(1..lane_count);
+            if lane >= free_lane {
+                lane += 1;
+            }
+
+            cars.push(Spanned::new(0..0, LevelCar {
+                beat: Some(beat),
+                time: None,
+                lane,
+                car: None,
+            }));
+        }
+
+        Ok(Level {
+            music,
+            bpm: beat_map.bpm,
+            offset: beat_map.beats[0],
+            speed: START_GAME_SPEED as f64,
+            cars,
+            speeds: Vec::new(),
+            events: vec![Spanned::new(0..0, LevelEvent {
+                beat: None,
+                time: Some(beat_map.duration),
+                event_type: LevelEventType::End,
+                text: None,
+            })],
+        })
+    }
+
+    // Used when the road or the bot cars of the game change, the level must still fit them
+    pub fn check_game(&self, lane_count: usize, bot_car_count: usize) -> Result<(), String> {
+        match self.cars.iter().flat_map(|car| check_car(car.get_ref(), lane_count, bot_car_count)).next() {
+            Some(problem) => Err(format!("the level does not fit the game, a car is wrong: {}", problem)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_tempo(&self) -> Tempo {
+        Tempo {
+            bpm: self.bpm as f32,
+            offset: self.offset as f32,
+        }
+    }
+
+    // Seconds from the start of the music of an entry placed at a beat or at a time
+    fn get_time(&self, beat: Option<f64>, time: Option<f64>) -> f32 {
+        match (beat, time) {
+            (Some(beat), _) => (self.offset + beat * 60.0 / self.bpm) as f32,
+            (None, time) => time.unwrap_or(0.0) as f32,
+        }
+    }
+
+    fn validate(&self, content: &str, lane_count: usize, bot_car_count: usize) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(problem) = self.get_tempo().check() {
+            problems.push(problem);
+        }
+        if !(self.speed > 0.0 && self.speed.is_finite()) {
+            problems.push("speed must be positive".to_string());
+        }
+        if self.music.as_ref().is_some_and(|music| music.trim().is_empty()) {
+            problems.push("music is empty".to_string());
+        }
+        if self.cars.is_empty() {
+            problems.push("the level has no car".to_string());
+        }
+
+        for car in &self.cars {
+            let line = get_line(content, car.span());
+            problems.extend(check_moment(car.get_ref().beat, car.get_ref().time).map(|problem| format!("line {}: {}", line, problem)));
+            problems.extend(check_car(car.get_ref(), lane_count, bot_car_count).into_iter().map(|problem| format!("line {}: {}", line, problem)));
+        }
+
+        for speed in &self.speeds {
+            let line = get_line(content, speed.span());
+            problems.extend(check_moment(speed.get_ref().beat, speed.get_ref().time).map(|problem| format!("line {}: {}", line, problem)));
+            if !(speed.get_ref().speed > 0.0 && speed.get_ref().speed.is_finite()) {
+                problems.push(format!("line {}: the speed must be positive", line));
+            }
+        }
+
+        let mut end_line = None;
+        for event in &self.events {
+            let line = get_line(content, event.span());
+            problems.extend(check_moment(event.get_ref().beat, event.get_ref().time).map(|problem| format!("line {}: {}", line, problem)));
+            match (event.get_ref().event_type, &event.get_ref().text) {
+                (LevelEventType::Message, None) => problems.push(format!("line {}: a message needs a text", line)),
+                (LevelEventType::End, Some(_)) => problems.push(format!("line {}: an end has no text", line)),
+                _ => {}
+            }
+            if event.get_ref().event_type == LevelEventType::End {
+                match end_line {
+                    Some(end_line) => problems.push(format!("line {}: the level already ends at line {}", line, end_line)),
+                    None => end_line = Some(line),
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+impl LevelTrack {
+    // The lanes and the cars of the file go from 1, the ones of the simulation from 0
+    pub fn from_level(level: &Level) -> LevelTrack {
+        let mut cars: Vec<PlannedCar> = level.cars.iter()
+            .map(|car| PlannedCar {
+                time: level.get_time(car.get_ref().beat, car.get_ref().time),
+                way: Way(car.get_ref().lane - 1),
+                texture_id: car.get_ref().car.map(|car| car - 1),
+            })
+            .collect();
+        cars.sort_by(|first, second| first.time.total_cmp(&second.time));
+
+        let mut speeds: Vec<(f32, f32)> = level.speeds.iter()
+            .map(|speed| (level.get_time(speed.get_ref().beat, speed.get_ref().time), speed.get_ref().speed as f32))
+            .collect();
+        speeds.sort_by(|first, second| first.0.total_cmp(&second.0));
+
+        let mut messages: Vec<(f32, String)> = level.events.iter()
+            .filter_map(|event| match (event.get_ref().event_type, &event.get_ref().text) {
+                (LevelEventType::Message, Some(text)) => Some((level.get_time(event.get_ref().beat, event.get_ref().time), text.clone())),
+                _ => None,
+            })
+            .collect();
+        messages.sort_by(|first, second| first.0.total_cmp(&second.0));
+
+        let end_time = level.events.iter()
+            .find(|event| event.get_ref().event_type == LevelEventType::End)
+            .map(|event| level.get_time(event.get_ref().beat, event.get_ref().time));
+
+        LevelTrack {
+            cars,
+            start_speed: level.speed as f32,
+            speeds,
+            messages,
+            end_time,
+        }
+    }
+
+    // Cars entering the road from `previous_time` until `time`, this time excluded
+    pub fn get_cars(&self, previous_time: f32, time: f32) -> &[PlannedCar] {
+        let first = self.cars.partition_point(|car| car.time < previous_time);
+        let last = self.cars.partition_point(|car| car.time < time);
+        &self.cars[first..last.max(first)]
+    }
+
+    pub fn get_start_speed(&self) -> f32 {
+        self.start_speed
+    }
+
+    // Speed of the last speed change before `time`, the start speed before the first one
+    pub fn get_speed(&self, time: f32) -> f32 {
+        let change_count = self.speeds.partition_point(|&(change_time, _)| change_time <= time);
+        match change_count {
+            0 => self.start_speed,
+            _ => self.speeds[change_count - 1].1,
+        }
+    }
+
+    // Last message shown at `time`, during LEVEL_MESSAGE_DURATION seconds
+    pub fn get_message(&self, time: f32) -> Option<&str> {
+        let message_count = self.messages.partition_point(|(message_time, _)| *message_time <= time);
+        match message_count {
+            0 => None,
+            _ => self.messages.get(message_count - 1)
+                .filter(|(message_time, _)| time < message_time + LEVEL_MESSAGE_DURATION)
+                .map(|(_, text)| text.as_str()),
+        }
+    }
+
+    // The level ends at its end event, without one when its last car has left the road
+    pub fn is_finished(&self, time: f32, bot_car_count: usize) -> bool {
+        match self.end_time {
+            Some(end_time) => time >= end_time,
+            None => bot_car_count == 0 && self.cars.last().is_none_or(|car| car.time < time),
+        }
+    }
+
+    // The messages do not change the run, they are not hashed
+    pub fn write_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_usize(self.cars.len());
+        for car in &self.cars {
+            hasher.write_f32(car.time);
+            hasher.write_usize(car.way.0);
+            // A random car is written as the index after every car
+            hasher.write_usize(car.texture_id.map_or(0, |texture_id| texture_id + 1));
+        }
+        hasher.write_f32(self.start_speed);
+        hasher.write_usize(self.speeds.len());
+        for &(time, speed) in &self.speeds {
+            hasher.write_f32(time);
+            hasher.write_f32(speed);
+        }
+        hasher.write_f32(self.end_time.unwrap_or(-1.0));
+    }
+}
+
+// Writes a first level of a music, from its beat map or from the analysis of the music without it,
+// next to the music without `output`, for a road of `lane_count` lanes. An existing level is never replaced,
+// it may have been edited by hand. Returns the level and the path of its file
+pub fn write_level(music_path: &Path, output: Option<&Path>, lane_count: usize) -> RustyResult<(Level, PathBuf)> {
+    let beat_map_path = PathBuf::from(BeatMap::get_path(&music_path.to_string_lossy()));
+    let beat_map = if beat_map_path.exists() {
+        let content = fs::read_to_string(&beat_map_path).map_err(RustyError::file("read the beat map", &beat_map_path))?;
+        BeatMap::parse(&content).map_err(|e| RustyError::BeatMap(format!("{}: {}", beat_map_path.display(), e)))?
+    } else {
+        beat_analysis::analyze_file(music_path)?
+    };
+
+    let output = output.map_or_else(|| PathBuf::from(Level::get_path(&music_path.to_string_lossy())), Path::to_path_buf);
+    if output.exists() {
+        return Err(RustyError::Level(format!("{} already exists, remove it or choose another --output", output.display())));
+    }
+
+    // The music is written from the directory of the level
+    let music = if output.parent() == music_path.parent() {
+        music_path.file_name().map(|file_name| file_name.to_string_lossy().to_string())
+    } else {
+        let music_path = fs::canonicalize(music_path).map_err(RustyError::file("find the music", music_path))?;
+        Some(music_path.to_string_lossy().to_string())
+    };
+
+    let level = Level::from_beat_map(&beat_map, music, lane_count)?;
+    level.save(&output)?;
+
+    Ok((level, output))
+}
+
+fn get_line(content: &str, span: Range<usize>) -> usize {
+    content[..span.start.min(content.len())].matches('\n').count() + 1
+}
+
+// Problem of the lane or of the car of a level car, on a road of `lane_count` lanes with `bot_car_count` bot cars
+fn check_car(car: &LevelCar, lane_count: usize, bot_car_count: usize) -> Vec<String> {
+    let mut problems = Vec::new();
+    if !(1..=lane_count).contains(&car.lane) {
+        problems.push(format!("the lane must be between 1 and {}", lane_count));
+    }
+    if car.car.is_some_and(|car| !(1..=bot_car_count).contains(&car)) {
+        problems.push(format!("the car must be between 1 and {}", bot_car_count));
+    }
+    problems
+}
+
+// Problem of the moment of an entry, which has a beat or a time
+fn check_moment(beat: Option<f64>, time: Option<f64>) -> Option<String> {
+    match (beat, time) {
+        (Some(_), Some(_)) => Some("an entry has a beat or a time, not both".to_string()),
+        (None, None) => Some("the beat or the time is missing".to_string()),
+        (Some(beat), None) if !(beat >= 0.0 && beat.is_finite()) => Some("the beat must be positive".to_string()),
+        (None, Some(time)) if !(time >= 0.0 && time.is_finite()) => Some("the time must be positive".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::rhythm::beat_map::Section;
+
+    use super::*;
+
+    const LANE_COUNT: usize = 3;
+    const BOT_CAR_COUNT: usize = 4;
+
+    const LEVEL: &str = "\
+bpm = 120.0
+speed = 400.0
+
+[[cars]]
+beat = 4
+lane = 1
+car = 2
+
+[[cars]]
+time = 3.0
+lane = 3
+
+[[events]]
+beat = 16
+type = \"end\"
+";
+
+    // 16 beats at 120 BPM, the first half is quiet
+    fn get_beat_map() -> BeatMap {
+        BeatMap {
+            bpm: 120.0,
+            duration: 8.0,
+            beats: (0..16).map(|beat| 0.25 + beat as f64 * 0.5).collect(),
+            sections: vec![
+                Section { start: 0.0, end: 4.0, intensity: 0.1 },
+                Section { start: 4.0, end: 8.0, intensity: 1.0 },
+            ],
+        }
+    }
+
+    fn check_problem(content: &str, problem: &str) {
+        let result = Level::parse(content, LANE_COUNT, BOT_CAR_COUNT);
+        assert!(result.as_ref().is_err_and(|problems| problems.iter().any(|e| e == problem)), "{:?} instead of {}", result, problem);
+    }
+
+    #[test]
+    fn level_is_parsed() {
+        let level = Level::parse(LEVEL, LANE_COUNT, BOT_CAR_COUNT).unwrap();
+        let track = LevelTrack::from_level(&level);
+
+        assert_eq!(track.get_cars(0.0, 10.0), [
+            PlannedCar { time: 2.0, way: Way(0), texture_id: Some(1) },
+            PlannedCar { time: 3.0, way: Way(2), texture_id: None },
+        ]);
+        assert_eq!(track.get_speed(5.0), 400.0);
+        assert!(!track.is_finished(7.9, 0));
+        assert!(track.is_finished(8.0, 2));
+    }
+
+    #[test]
+    fn created_level_is_saved_and_parsed_again() {
+        let level = Level::from_beat_map(&get_beat_map(), Some("track.ogg".to_string()), LANE_COUNT).unwrap();
+
+        // No car in the quiet part, the end is the end of the music
+        assert!(!level.cars.is_empty());
+        assert!(level.cars.iter().all(|car| car.get_ref().beat.is_some_and(|beat| beat >= 8.0)));
+        assert_eq!(LevelTrack::from_level(&level).end_time, Some(8.0));
+
+        let content = format!("{}{}", LEVEL_FILE_HEADER, toml::to_string(&level).unwrap());
+        assert_eq!(Level::parse(&content, LANE_COUNT, BOT_CAR_COUNT), Ok(level));
+    }
+
+    #[test]
+    fn created_level_needs_two_lanes() {
+        assert!(Level::from_beat_map(&get_beat_map(), None, 1).is_err());
+        assert!(Level::from_beat_map(&get_beat_map(), None, 2).is_ok());
+    }
+
+    #[test]
+    fn problems_give_their_line() {
+        check_problem(&LEVEL.replace("lane = 3", "lane = 4"), "line 9: the lane must be between 1 and 3");
+        check_problem(&LEVEL.replace("car = 2", "car = 5"), "line 4: the car must be between 1 and 4");
+        check_problem(&LEVEL.replace("car = 2", "car = 0"), "line 4: the car must be between 1 and 4");
+        check_problem(&LEVEL.replace("time = 3.0", "time = 3.0\nbeat = 6"), "line 9: an entry has a beat or a time, not both");
+        check_problem(&format!("{}\n[[events]]\ntime = 20.0\ntype = \"end\"\n", LEVEL), "line 17: the level already ends at line 13");
+    }
+
+    #[test]
+    fn level_must_fit_the_game() {
+        let level = Level::parse(LEVEL, LANE_COUNT, BOT_CAR_COUNT).unwrap();
+        assert!(level.check_game(LANE_COUNT, BOT_CAR_COUNT).is_ok());
+        assert!(level.check_game(2, BOT_CAR_COUNT).is_err());
+        assert!(level.check_game(LANE_COUNT, 1).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::hash::Hasher;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BEATS_PER_BAR, DEFAULT_BPM};
+use crate::game::rhythm::beat_map::BeatMap;
+use crate::utils::stable_hasher::StableHasher;
+
+pub mod beat_analysis;
+pub mod beat_map;
+pub mod level;
+
+const MIN_BPM: f32 = 30.0;
+const MAX_BPM: f32 = 300.0;
+
+/// Tempo of a music, declared in the asset manifest or in the configuration
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tempo {
+    // Beats per minute
+    pub bpm: f32,
+    // Time of the first beat from the start of the music, in seconds
+    #[serde(default)]
+    pub offset: f32,
+}
+
+/// Beats of the game music, the rhythm mode spawns the cars on them. The times are
+/// in seconds from the start of the music, the beats start again when the music loops
+#[derive(Clone, PartialEq, Debug)]
+pub struct BeatTrack {
+    // Beats of one play of the music in seconds, in the order of their time, there is at least one
+    beats: Vec<f32>,
+    duration: f32,
+}
+
+impl Tempo {
+    // Problem of a tempo written by hand, none if it can be used
+    pub fn check(&self) -> Option<String> {
+        if !(MIN_BPM..=MAX_BPM).contains(&self.bpm) {
+            Some(format!("bpm must be between {} and {}", MIN_BPM, MAX_BPM))
+        } else if self.offset < 0.0 {
+            Some("offset must be positive".to_string())
+        } else {
+            None
+        }
+    }
+
+    pub fn get_beat_duration(&self) -> f32 {
+        60.0 / self.bpm
+    }
+}
+
+impl Default for Tempo {
+    fn default() -> Tempo {
+        Tempo {
+            bpm: DEFAULT_BPM,
+            offset: 0.0,
+        }
+    }
+}
+
+impl BeatTrack {
+    // Regular beats of a declared tempo, `duration` is the duration of the music.
+    // An offset after the end of the music starts again from its start
+    pub fn from_tempo(tempo: Tempo, duration: f32) -> BeatTrack {
+        let beat_duration = tempo.get_beat_duration();
+        let duration = duration.max(beat_duration);
+        let offset = tempo.offset % duration;
+
+        let beat_count = (((duration - offset) / beat_duration).ceil() as usize).max(1);
+        let beats = (0..beat_count).map(|beat| offset + beat as f32 * beat_duration).collect();
+
+        BeatTrack {
+            beats,
+            duration,
+        }
+    }
+
+    // Beats detected in the music
+    pub fn from_beat_map(beat_map: &BeatMap) -> BeatTrack {
+        // A beat map is checked when it is loaded, it has at least one beat
+        BeatTrack {
+            beats: beat_map.beats.iter().map(|&time| time as f32).collect(),
+            duration: beat_map.duration as f32,
+        }
+    }
+
+    // Number of beats played from the start of the music until `music_time`, this beat included
+    pub fn get_beat_count(&self, music_time: f32) -> u64 {
+        if music_time < 0.0 {
+            return 0;
+        }
+
+        let loop_count = (music_time / self.duration).floor();
+        let loop_time = music_time - loop_count * self.duration;
+        let loop_beat_count = self.beats.partition_point(|&time| time <= loop_time);
+
+        loop_count as u64 * self.beats.len() as u64 + loop_beat_count as u64
+    }
+
+    // Time of a beat from the start of the music, the first beat has the index 0
+    pub fn get_beat_time(&self, beat: u64) -> f32 {
+        let loop_count = beat / self.beats.len() as u64;
+        loop_count as f32 * self.duration + self.beats[(beat % self.beats.len() as u64) as usize]
+    }
+
+    // Cars spawned on a beat: two on the first beat of a bar and one on the others.
+    // The bars start again with the music, its first beat is always the first beat of a bar
+    pub fn get_car_count(&self, beat: u64) -> u32 {
+        if (beat % self.beats.len() as u64).is_multiple_of(BEATS_PER_BAR) { 2 } else { 1 }
+    }
+
+    // Time between `music_time` and the closest beat, in seconds
+    pub fn get_beat_distance(&self, music_time: f32) -> f32 {
+        let beat_count = self.get_beat_count(music_time);
+        let next_distance = self.get_beat_time(beat_count) - music_time;
+        match beat_count {
+            0 => next_distance,
+            _ => next_distance.min(music_time - self.get_beat_time(beat_count - 1)),
+        }
+    }
+
+    // Progress from the last beat to the next one, from 0 (on the beat) to 1
+    pub fn get_beat_phase(&self, music_time: f32) -> f32 {
+        let beat_count = self.get_beat_count(music_time);
+        if beat_count == 0 {
+            return 0.0;
+        }
+
+        let previous_time = self.get_beat_time(beat_count - 1);
+        let next_time = self.get_beat_time(beat_count);
+        ((music_time - previous_time) / (next_time - previous_time)).clamp(0.0, 1.0)
+    }
+
+    pub fn write_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_usize(self.beats.len());
+        for &time in &self.beats {
+            hasher.write_f32(time);
+        }
+        hasher.write_f32(self.duration);
+    }
+}
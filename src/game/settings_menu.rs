@@ -0,0 +1,174 @@
+use macroquad::input::{get_last_key_pressed, is_key_pressed, KeyCode};
+
+use crate::config::{KEY_GAME, RESERVED_KEYS};
+use crate::keyboard::{GAME_ACTION, GameAction, get_game_action_from_key_code, get_key_codes_from_game_action, get_str_from_key_code};
+
+#[derive(PartialEq)]
+pub enum SettingsChange {
+    None,
+    KeyBindings,
+    AssetPack,
+}
+
+/// Screen of the main menu where the keys of each action and the asset pack are changed.
+/// It is driven by fixed keys read on the main thread, so a wrong binding can always be fixed
+pub struct SettingsMenu {
+    // Index of the selected row: the actions of GAME_ACTION, then the asset pack
+    selected_row: usize,
+    // Packs found in the pack directories
+    asset_packs: Vec<String>,
+    // The next key pressed is added to the selected action
+    waiting_for_key: bool,
+    // Result of the last change, displayed under the bindings
+    message: Option<String>,
+}
+
+impl SettingsMenu {
+    pub fn new(asset_packs: Vec<String>) -> SettingsMenu {
+        SettingsMenu {
+            selected_row: 0,
+            asset_packs,
+            waiting_for_key: false,
+            message: None,
+        }
+    }
+
+    // None when the asset pack is selected
+    pub fn get_selected_action(&self) -> Option<GameAction> {
+        GAME_ACTION.get(self.selected_row).map(|&(action, _)| action)
+    }
+
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting_for_key
+    }
+
+    pub fn get_message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    // Reads the keys pressed during the frame and changes the settings
+    pub fn update(&mut self, key_bindings: &mut Vec<(KeyCode, GameAction)>, asset_pack: &mut Option<String>) -> SettingsChange {
+        if self.waiting_for_key {
+            return match get_last_key_pressed() {
+                // Escape cancels the new key
+                Some(KeyCode::Escape) => {
+                    self.waiting_for_key = false;
+                    self.message = None;
+                    SettingsChange::None
+                }
+                Some(key) => {
+                    self.waiting_for_key = false;
+                    self.add_key(key_bindings, key)
+                }
+                None => SettingsChange::None,
+            };
+        }
+
+        let row_count = GAME_ACTION.len() + 1;
+        let mut change = SettingsChange::None;
+
+        if is_key_pressed(KeyCode::Up) {
+            self.selected_row = (self.selected_row + row_count - 1) % row_count;
+            self.message = None;
+        } else if is_key_pressed(KeyCode::Down) {
+            self.selected_row = (self.selected_row + 1) % row_count;
+            self.message = None;
+        } else if is_key_pressed(KeyCode::R) {
+            *key_bindings = KEY_GAME.to_vec();
+            self.message = Some("Default keys restored".to_string());
+            change = SettingsChange::KeyBindings;
+        } else if self.get_selected_action().is_some() {
+            if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+                self.waiting_for_key = true;
+                self.message = None;
+            } else if is_key_pressed(KeyCode::Backspace) || is_key_pressed(KeyCode::Delete) {
+                change = self.remove_last_key(key_bindings);
+            }
+        } else if is_key_pressed(KeyCode::Right) || is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            change = self.change_asset_pack(asset_pack, 1);
+        } else if is_key_pressed(KeyCode::Left) {
+            change = self.change_asset_pack(asset_pack, -1);
+        }
+
+        change
+    }
+
+    // The default assets come before the packs
+    fn change_asset_pack(&mut self, asset_pack: &mut Option<String>, offset: isize) -> SettingsChange {
+        let mut choices: Vec<Option<String>> = vec![None];
+        choices.extend(self.asset_packs.iter().cloned().map(Some));
+
+        let index = choices.iter().position(|choice| choice == asset_pack).unwrap_or(0);
+        let new_index = (index as isize + offset).rem_euclid(choices.len() as isize) as usize;
+        if new_index == index {
+            self.message = Some("No asset pack found in the packs directory".to_string());
+            return SettingsChange::None;
+        }
+
+        *asset_pack = choices[new_index].clone();
+        self.message = None;
+        SettingsChange::AssetPack
+    }
+
+    fn add_key(&mut self, key_bindings: &mut Vec<(KeyCode, GameAction)>, key: KeyCode) -> SettingsChange {
+        let selected_action = match self.get_selected_action() {
+            Some(action) => action,
+            None => return SettingsChange::None,
+        };
+
+        if let Some(&(_, description)) = RESERVED_KEYS.iter().find(|&&(reserved_key, _)| reserved_key == key) {
+            self.message = Some(format!("{} {}", get_str_from_key_code(key), description));
+            return SettingsChange::None;
+        }
+
+        // A key can only do one action, the player has to remove it from the other action first
+        match get_game_action_from_key_code(key_bindings, key) {
+            Some(action) if action == selected_action => {
+                self.message = Some(format!("{} is already bound to {}",
+                                            get_str_from_key_code(key),
+                                            SettingsMenu::get_action_name(action)));
+                SettingsChange::None
+            }
+            Some(action) => {
+                self.message = Some(format!("{} is already used by {}",
+                                            get_str_from_key_code(key),
+                                            SettingsMenu::get_action_name(action)));
+                SettingsChange::None
+            }
+            None => {
+                key_bindings.push((key, selected_action));
+                self.message = None;
+                SettingsChange::KeyBindings
+            }
+        }
+    }
+
+    fn remove_last_key(&mut self, key_bindings: &mut Vec<(KeyCode, GameAction)>) -> SettingsChange {
+        let selected_action = match self.get_selected_action() {
+            Some(action) => action,
+            None => return SettingsChange::None,
+        };
+
+        // Without key, the action could not be done anymore
+        if get_key_codes_from_game_action(key_bindings, selected_action).len() <= 1 {
+            self.message = Some(format!("{} needs at least one key", SettingsMenu::get_action_name(selected_action)));
+            return SettingsChange::None;
+        }
+
+        if let Some(index) = key_bindings.iter().rposition(|&(_, action)| action == selected_action) {
+            key_bindings.remove(index);
+        }
+        self.message = None;
+        SettingsChange::KeyBindings
+    }
+
+    fn get_action_name(game_action: GameAction) -> String {
+        let mut name = String::new();
+        for &(action, description) in &GAME_ACTION {
+            if action == game_action {
+                name = description.trim_end_matches(": ").to_string();
+            }
+        }
+        name
+    }
+}
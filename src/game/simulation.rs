@@ -0,0 +1,688 @@
+use std::hash::Hasher;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::config::{ON_BEAT_BONUS, ON_BEAT_TOLERANCE, SCORE_PER_DISTANCE, SIMULATION_STEP_DURATION, TIME_ATTACK_DURATION};
+use crate::config::config_file::GameConfig;
+use crate::game::car::{Car, PLAYER_CAR_X_POSITION, Way};
+use crate::game::car::bot_car::BotCar;
+use crate::game::car::bot_manager::BotManager;
+use crate::game::car::hitbox::CarHitboxes;
+use crate::game::car::player_car::PlayerCar;
+use crate::game::difficulty::DifficultyCurve;
+use crate::game::game_mode::GameMode;
+use crate::game::rhythm::BeatTrack;
+use crate::game::rhythm::level::LevelTrack;
+use crate::keyboard::GameAction;
+use crate::utils::rusty_error::RustyResult;
+use crate::utils::stable_hasher::StableHasher;
+
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub enum GameState {
+    NotStarted,
+    Running,
+    Pause,
+    GameOver,
+}
+
+// Events emitted by the simulation, the rendering layer reacts to them
+// (sounds, screens...) without having to inspect the whole state
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub enum SimulationEvent {
+    StateChanged { previous: GameState, current: GameState },
+    QuitRequested,
+}
+
+/// Game logic without any rendering or audio, it only knows the lanes, the cars,
+/// the speed and the score, so it can run without a window
+pub struct Simulation {
+    player_car: PlayerCar,
+    bot_manager: BotManager,
+    // Vertical position of each lane, the cars only know the index of their lane
+    lanes: Vec<f32>,
+    lane_change_duration: f32,
+    hitboxes: CarHitboxes,
+    pixel_collision: bool,
+    difficulty_curve: DifficultyCurve,
+    distance_between_cars: f32,
+    mode: GameMode,
+    // Beats of the game music, the rhythm mode follows them
+    beat_track: BeatTrack,
+    // Time since the game music started, it is played from its start when the run starts or resumes
+    music_time: f32,
+    // Lane changes on the beat during the run, in the rhythm mode
+    on_beat_count: u32,
+    // Hand-made level, its cars and its speeds replace the random traffic and the difficulty curve
+    level: Option<LevelTrack>,
+    // The run ended at the end of the level
+    level_complete: bool,
+    // The score grows with the travelled distance, it is kept as a float to not lose
+    // the fractional points of each step
+    score: f32,
+    session_record: u32,
+    speed: f32,
+    // Highest speed reached during the run
+    max_speed: f32,
+    // Simulated time spent in the current run, the speed is computed from it
+    game_time: f32,
+    // Number of steps since the start of the run (pause included), used to date the actions of a replay
+    run_step: u32,
+    state: GameState,
+    // Point of the collision which ended the run
+    game_over_collision: Option<(f32, f32)>,
+    // Seed given by the player, if there is none a new seed is drawn for each run
+    fixed_seed: Option<u64>,
+    seed: u64,
+    // Every random choice of a run comes from this generator, so a run can be reproduced with its seed
+    rng: ChaCha8Rng,
+}
+
+impl Simulation {
+    pub fn new(game_config: &GameConfig, hitboxes: CarHitboxes, beat_track: BeatTrack, level: Option<LevelTrack>, fixed_seed: Option<u64>) -> Simulation {
+        let difficulty_curve = game_config.difficulty;
+        let start_speed = level.as_ref().map_or(difficulty_curve.get_start_speed(), LevelTrack::get_start_speed);
+
+        Simulation {
+            player_car: PlayerCar::new(&game_config.lanes),
+            bot_manager: BotManager::new(game_config.lanes.len(), hitboxes.bot_cars.len(), game_config.distance_between_cars),
+            lanes: game_config.lanes.clone(),
+            lane_change_duration: game_config.lane_change_duration,
+            hitboxes,
+            pixel_collision: game_config.pixel_collision,
+            difficulty_curve,
+            distance_between_cars: game_config.distance_between_cars,
+            mode: game_config.mode,
+            beat_track,
+            music_time: 0.0,
+            on_beat_count: 0,
+            level,
+            level_complete: false,
+            score: 0.0,
+            session_record: 0,
+            speed: start_speed,
+            max_speed: start_speed,
+            game_time: 0.0,
+            run_step: 0,
+            state: GameState::NotStarted,
+            game_over_collision: None,
+            fixed_seed,
+            seed: 0,
+            rng: ChaCha8Rng::seed_from_u64(0),
+        }
+    }
+
+    /// Advances the simulation by `delta_time` seconds with the action received during this step,
+    /// the result only depends on the steps, so `delta_time` should always be the same duration
+    pub fn step(&mut self, delta_time: f32, game_action: GameAction) -> RustyResult<Vec<SimulationEvent>> {
+        let mut events: Vec<SimulationEvent> = Vec::new();
+
+        // If the game is not running, player can quit the game
+        if game_action == GameAction::Quit && self.state != GameState::Running {
+            events.push(SimulationEvent::QuitRequested);
+            return Ok(events);
+        }
+
+        let previous_state: GameState = self.state;
+
+        match self.state {
+            GameState::NotStarted => {
+                if game_action == GameAction::PauseResume {
+                    self.start();
+                }
+            }
+            GameState::Running => {
+                self.move_player_car(game_action);
+                self.player_car.update_position(delta_time, self.lane_change_duration, &self.lanes);
+
+                // The time only goes on while the game is running, so the pause has no effect on the speed
+                self.game_time += delta_time;
+                let previous_music_time = self.music_time;
+                self.music_time += delta_time;
+
+                // The level is placed on its music, it follows the music time
+                self.speed = match &self.level {
+                    Some(level) => level.get_speed(self.music_time),
+                    None => self.difficulty_curve.get_speed(self.game_time),
+                };
+                self.max_speed = self.max_speed.max(self.speed);
+
+                // The score is calculated with the distance travelled during the step
+                self.score += SCORE_PER_DISTANCE * self.speed * delta_time;
+
+                for bot_car in self.bot_manager.bot_car_list.iter_mut() {
+                    bot_car.set_speed(self.speed);
+                }
+
+                self.spawn_bot_cars(previous_music_time)?;
+
+                // The player car is colliding with a bot car ?
+                if let Some(collision_position) = self.manage_bot_cars(delta_time) {
+                    self.game_over_collision = Some(collision_position);
+                    self.state = GameState::GameOver;
+                    self.stop();
+                } else if self.get_remaining_time().is_some_and(|remaining_time| remaining_time <= 0.0) {
+                    self.state = GameState::GameOver;
+                    self.stop();
+                } else if self.level.as_ref().is_some_and(|level| level.is_finished(self.music_time, self.bot_manager.bot_car_list.len())) {
+                    self.level_complete = true;
+                    self.state = GameState::GameOver;
+                    self.stop();
+                } else if game_action == GameAction::PauseResume {
+                    self.state = GameState::Pause;
+                }
+            }
+            GameState::Pause => {
+                if game_action == GameAction::PauseResume {
+                    // The game music is stopped during the pause, it starts again from its start
+                    // (the audio can not resume a music). A level is placed on its music, its run starts again
+                    self.music_time = 0.0;
+                    if self.level.is_some() {
+                        self.reset_run();
+                    }
+                    self.state = GameState::Running;
+                }
+            }
+            GameState::GameOver => {
+                if game_action == GameAction::PauseResume {
+                    self.speed = self.get_start_speed();
+
+                    self.bot_manager.bot_car_list.clear();
+                    self.game_over_collision = None;
+                    self.state = GameState::NotStarted;
+                }
+            }
+        }
+
+        if previous_state == GameState::Running || previous_state == GameState::Pause {
+            self.run_step += 1;
+        }
+
+        if previous_state != self.state {
+            events.push(SimulationEvent::StateChanged {
+                previous: previous_state,
+                current: self.state,
+            });
+        }
+
+        Ok(events)
+    }
+
+    pub fn get_state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn get_score(&self) -> u32 {
+        self.score as u32
+    }
+
+    pub fn get_session_record(&self) -> u32 {
+        self.session_record
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn get_max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    pub fn get_game_time(&self) -> f32 {
+        self.game_time
+    }
+
+    pub fn get_player_car(&self) -> &PlayerCar {
+        &self.player_car
+    }
+
+    // The new settings are used from the next step, the current run goes on
+    pub fn set_game_config(&mut self, game_config: &GameConfig) {
+        self.difficulty_curve = game_config.difficulty;
+        self.distance_between_cars = game_config.distance_between_cars;
+        self.mode = game_config.mode;
+        self.lane_change_duration = game_config.lane_change_duration;
+        self.pixel_collision = game_config.pixel_collision;
+        self.bot_manager.set_distance_between_cars(game_config.distance_between_cars);
+
+        // The cars are put back on the new road
+        if self.lanes != game_config.lanes {
+            self.lanes = game_config.lanes.clone();
+            self.bot_manager.set_lane_count(self.lanes.len());
+            self.player_car = PlayerCar::new(&self.lanes);
+            self.game_over_collision = None;
+        }
+
+        if self.state == GameState::NotStarted {
+            self.speed = self.get_start_speed();
+        }
+    }
+
+    // Used when the assets are reloaded, the next beats come from the new music
+    pub fn set_beat_track(&mut self, beat_track: BeatTrack) {
+        self.beat_track = beat_track;
+    }
+
+    // Used when the assets are reloaded, the bot cars keep the index of their texture
+    // while the new assets have it
+    pub fn set_hitboxes(&mut self, hitboxes: CarHitboxes) {
+        self.bot_manager.set_bot_texture_count(hitboxes.bot_cars.len());
+        self.hitboxes = hitboxes;
+    }
+
+    pub fn get_hitboxes(&self) -> &CarHitboxes {
+        &self.hitboxes
+    }
+
+    pub fn is_pixel_collision(&self) -> bool {
+        self.pixel_collision
+    }
+
+    pub fn get_lane_change_duration(&self) -> f32 {
+        self.lane_change_duration
+    }
+
+    pub fn get_lane_positions(&self) -> &[f32] {
+        &self.lanes
+    }
+
+    pub fn get_bot_cars(&self) -> &[BotCar] {
+        &self.bot_manager.bot_car_list
+    }
+
+    // Time left before the end of the run, for the modes with a limited time
+    pub fn get_remaining_time(&self) -> Option<f32> {
+        match self.mode {
+            GameMode::Endless | GameMode::Rhythm => None,
+            GameMode::TimeAttack => Some((TIME_ATTACK_DURATION - self.game_time).max(0.0)),
+        }
+    }
+
+    // Progress from the last beat of the music to the next one, in the rhythm mode
+    pub fn get_beat_phase(&self) -> Option<f32> {
+        match self.mode {
+            GameMode::Rhythm => Some(self.beat_track.get_beat_phase(self.music_time)),
+            GameMode::Endless | GameMode::TimeAttack => None,
+        }
+    }
+
+    pub fn get_on_beat_count(&self) -> u32 {
+        self.on_beat_count
+    }
+
+    // Message of the level shown at this moment of the run
+    pub fn get_level_message(&self) -> Option<&str> {
+        self.level.as_ref().and_then(|level| level.get_message(self.music_time))
+    }
+
+    pub fn is_level_complete(&self) -> bool {
+        self.level_complete
+    }
+
+    pub fn get_game_over_collision(&self) -> Option<(f32, f32)> {
+        self.game_over_collision
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_run_step(&self) -> u32 {
+        self.run_step
+    }
+
+    /// Hash of every setting changing the course of a run, a replay recorded with
+    /// another hash will not give the same run
+    pub fn get_config_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+
+        self.difficulty_curve.write_hash(&mut hasher);
+        hasher.write_f32(SCORE_PER_DISTANCE);
+        hasher.write_f32(SIMULATION_STEP_DURATION);
+        hasher.write_f32(self.distance_between_cars);
+        self.mode.write_hash(&mut hasher);
+        // The music only changes the run in the rhythm mode
+        if self.mode == GameMode::Rhythm {
+            self.beat_track.write_hash(&mut hasher);
+        }
+        hasher.write_u8(self.level.is_some() as u8);
+        if let Some(level) = &self.level {
+            level.write_hash(&mut hasher);
+        }
+        hasher.write_usize(self.lanes.len());
+        for &position in &self.lanes {
+            hasher.write_f32(position);
+        }
+        hasher.write_f32(self.lane_change_duration);
+        hasher.write_f32(WINDOW_WIDTH);
+        hasher.write_f32(WINDOW_HEIGHT);
+        hasher.write_f32(PLAYER_CAR_X_POSITION);
+        // The bot texture count changes the random choices, the hitboxes change the collisions
+        hasher.write_usize(self.bot_manager.get_bot_texture_count());
+        hasher.write_u8(self.pixel_collision as u8);
+        self.hitboxes.player_car.write_hash(&mut hasher, self.pixel_collision);
+        for hitbox in &self.hitboxes.bot_cars {
+            hitbox.write_hash(&mut hasher, self.pixel_collision);
+        }
+
+        hasher.finish()
+    }
+
+    fn get_start_speed(&self) -> f32 {
+        self.level.as_ref().map_or(self.difficulty_curve.get_start_speed(), LevelTrack::get_start_speed)
+    }
+
+    fn start(&mut self) {
+        self.run_step = 0;
+        self.seed = self.fixed_seed.unwrap_or_else(|| rand::thread_rng().gen());
+        self.reset_run();
+
+        self.state = GameState::Running;
+    }
+
+    // The run starts again with its seed, its steps go on to date the next actions of its replay
+    fn reset_run(&mut self) {
+        self.score = 0.0;
+        self.game_time = 0.0;
+        self.music_time = 0.0;
+        self.on_beat_count = 0;
+        self.level_complete = false;
+        self.speed = self.get_start_speed();
+
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.player_car = PlayerCar::new(&self.lanes);
+        self.bot_manager.bot_car_list.clear();
+    }
+
+    fn stop(&mut self) {
+        if self.session_record < self.get_score() {
+            self.session_record = self.get_score();
+        }
+    }
+
+    fn move_player_car(&mut self, game_action: GameAction) {
+        // Get the new way if the player car can move
+        if let Some(new_way) = self.get_destination_way(game_action, self.player_car.get_way()) {
+            self.player_car.set_way(new_way);
+
+            if self.mode == GameMode::Rhythm && self.beat_track.get_beat_distance(self.music_time) <= ON_BEAT_TOLERANCE {
+                self.score += ON_BEAT_BONUS;
+                self.on_beat_count += 1;
+            }
+        }
+    }
+
+    // Lane reached from `way` with the action, none if the action does not move the car
+    // or if the car is already on the side of the road
+    pub fn get_destination_way(&self, game_action: GameAction, way: Way) -> Option<Way> {
+        let Way(lane) = way;
+        match game_action {
+            GameAction::Up if lane > 0 => Some(Way(lane - 1)),
+            GameAction::Down if lane + 1 < self.lanes.len() => Some(Way(lane + 1)),
+            _ => None,
+        }
+    }
+
+    // Cars to spawn during the step. The bot manager keeps the distance between the cars, it tries at each step
+    // in the other modes, the rhythm mode only tries on the beats played during the step
+    fn get_spawn_count(&self, previous_music_time: f32) -> u32 {
+        if self.mode != GameMode::Rhythm {
+            return 1;
+        }
+
+        let first_beat = self.beat_track.get_beat_count(previous_music_time);
+        let last_beat = self.beat_track.get_beat_count(self.music_time);
+        (first_beat..last_beat).map(|beat| self.beat_track.get_car_count(beat)).sum()
+    }
+
+    // A level gives the cars entering the road during the step, otherwise they are drawn randomly
+    fn spawn_bot_cars(&mut self, previous_music_time: f32) -> RustyResult<()> {
+        match &self.level {
+            Some(level) => {
+                for planned_car in level.get_cars(previous_music_time, self.music_time) {
+                    self.bot_manager.spawn_level_car(planned_car, &mut self.rng)?;
+                }
+            }
+            None => {
+                for _ in 0..self.get_spawn_count(previous_music_time) {
+                    self.bot_manager.spawn_car(&mut self.rng)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn manage_bot_cars(&mut self, delta_time: f32) -> Option<(f32, f32)> {
+        // The collision is the first contact of the step, the cars are put back at this moment
+        let mut first_collision: Option<(f32, (f32, f32))> = None;
+        for bot_car in self.bot_manager.bot_car_list.iter_mut() {
+            bot_car.update_position(delta_time);
+            if let Some((progress, point)) = bot_car.is_colliding(&self.player_car, &self.lanes, &self.hitboxes, self.pixel_collision) {
+                if first_collision.is_none_or(|(first_progress, _)| progress < first_progress) {
+                    first_collision = Some((progress, point));
+                }
+            }
+        }
+
+        let is_colliding = first_collision.map(|(progress, point)| {
+            for bot_car in self.bot_manager.bot_car_list.iter_mut() {
+                bot_car.rewind_position(progress);
+            }
+            self.player_car.rewind_position(progress);
+            point
+        });
+
+        let hitboxes = &self.hitboxes;
+        self.bot_manager.bot_car_list.retain(|bot_car| {
+            !bot_car.is_out_of_screen(hitboxes)
+        });
+
+        is_colliding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::car::hitbox::Hitbox;
+    use crate::game::rhythm::level::Level;
+    use crate::game::rhythm::Tempo;
+
+    const SEED: u64 = 42;
+    // Steps of a run long enough to meet many cars
+    const STEP_COUNT: u32 = 3000;
+
+    fn new_simulation(fixed_seed: Option<u64>) -> Simulation {
+        new_level_simulation(fixed_seed, None)
+    }
+
+    fn new_level_simulation(fixed_seed: Option<u64>, level: Option<LevelTrack>) -> Simulation {
+        let hitboxes = CarHitboxes {
+            player_car: Hitbox::from_size(150, 73),
+            bot_cars: vec![Hitbox::from_size(158, 75); 3],
+        };
+        Simulation::new(&GameConfig::default(), hitboxes, BeatTrack::from_tempo(Tempo::default(), 10.0), level, fixed_seed)
+    }
+
+    // Steps until the run has lasted `duration` seconds more
+    fn run_for(simulation: &mut Simulation, duration: f32) {
+        let end_time = simulation.get_game_time() + duration;
+        while simulation.get_game_time() < end_time {
+            step(simulation, GameAction::None);
+        }
+    }
+
+    fn step(simulation: &mut Simulation, game_action: GameAction) -> Vec<SimulationEvent> {
+        simulation.step(SIMULATION_STEP_DURATION, game_action).unwrap()
+    }
+
+    // The player changes lane regularly, the same actions at the same steps for every run
+    fn get_action(step: u32) -> GameAction {
+        match step % 240 {
+            0 => GameAction::Up,
+            120 => GameAction::Down,
+            _ => GameAction::None,
+        }
+    }
+
+    #[test]
+    fn state_transitions() {
+        let mut simulation = new_simulation(Some(SEED));
+        assert_eq!(simulation.get_state(), GameState::NotStarted);
+
+        // Nothing happens before the start
+        assert!(step(&mut simulation, GameAction::Up).is_empty());
+        assert_eq!(simulation.get_state(), GameState::NotStarted);
+
+        assert_eq!(step(&mut simulation, GameAction::PauseResume), vec![SimulationEvent::StateChanged {
+            previous: GameState::NotStarted,
+            current: GameState::Running,
+        }]);
+        assert_eq!(simulation.get_seed(), SEED);
+
+        // The player cannot quit during the run
+        assert!(step(&mut simulation, GameAction::Quit).is_empty());
+        assert_eq!(simulation.get_state(), GameState::Running);
+
+        assert_eq!(step(&mut simulation, GameAction::PauseResume), vec![SimulationEvent::StateChanged {
+            previous: GameState::Running,
+            current: GameState::Pause,
+        }]);
+
+        // The time stops during the pause
+        let game_time = simulation.get_game_time();
+        step(&mut simulation, GameAction::None);
+        assert_eq!(simulation.get_game_time(), game_time);
+
+        assert_eq!(step(&mut simulation, GameAction::PauseResume), vec![SimulationEvent::StateChanged {
+            previous: GameState::Pause,
+            current: GameState::Running,
+        }]);
+        step(&mut simulation, GameAction::None);
+        assert!(simulation.get_game_time() > game_time);
+
+        step(&mut simulation, GameAction::PauseResume);
+        assert_eq!(step(&mut simulation, GameAction::Quit), vec![SimulationEvent::QuitRequested]);
+    }
+
+    #[test]
+    fn same_seed_and_actions_give_the_same_run() {
+        let mut first = new_simulation(Some(SEED));
+        let mut second = new_simulation(Some(SEED));
+        step(&mut first, GameAction::PauseResume);
+        step(&mut second, GameAction::PauseResume);
+
+        for step_index in 0..STEP_COUNT {
+            let first_events = step(&mut first, get_action(step_index));
+            let second_events = step(&mut second, get_action(step_index));
+            assert_eq!(first_events, second_events);
+
+            assert_eq!(first.get_state(), second.get_state());
+            assert_eq!(first.get_score(), second.get_score());
+            assert_eq!(first.get_speed(), second.get_speed());
+            assert_eq!(first.get_player_car().get_y_position(), second.get_player_car().get_y_position());
+            let get_bot_cars = |simulation: &Simulation| simulation.get_bot_cars().iter()
+                .map(|bot_car| (bot_car.get_way(), bot_car.get_texture_id(), bot_car.x_position))
+                .collect::<Vec<_>>();
+            assert_eq!(get_bot_cars(&first), get_bot_cars(&second));
+        }
+        assert_eq!(first.get_game_over_collision(), second.get_game_over_collision());
+        assert_eq!(first.get_config_hash(), second.get_config_hash());
+    }
+
+    #[test]
+    fn another_seed_gives_another_traffic() {
+        let mut first = new_simulation(Some(SEED));
+        let mut second = new_simulation(Some(SEED + 1));
+        step(&mut first, GameAction::PauseResume);
+        step(&mut second, GameAction::PauseResume);
+
+        let mut first_ways = Vec::new();
+        let mut second_ways = Vec::new();
+        for _ in 0..STEP_COUNT {
+            step(&mut first, GameAction::None);
+            step(&mut second, GameAction::None);
+            first_ways.extend(first.get_bot_cars().iter().map(Car::get_way));
+            second_ways.extend(second.get_bot_cars().iter().map(Car::get_way));
+        }
+        assert_ne!(first_ways, second_ways);
+    }
+
+    #[test]
+    fn collision_ends_the_run() {
+        let mut simulation = new_simulation(Some(SEED));
+        step(&mut simulation, GameAction::PauseResume);
+
+        // The player car stays in its lane, a car of this lane ends the run
+        let mut events = Vec::new();
+        for _ in 0..STEP_COUNT {
+            events = step(&mut simulation, GameAction::None);
+            if simulation.get_state() != GameState::Running {
+                break;
+            }
+        }
+
+        assert_eq!(events, vec![SimulationEvent::StateChanged {
+            previous: GameState::Running,
+            current: GameState::GameOver,
+        }]);
+        let (collision_x, collision_y) = simulation.get_game_over_collision().unwrap();
+        assert!((collision_y - simulation.get_lane_positions()[1]).abs() < 73.0 / 2.0);
+        assert!(collision_x >= PLAYER_CAR_X_POSITION);
+        assert!(simulation.get_score() > 0);
+        assert_eq!(simulation.get_session_record(), simulation.get_score());
+
+        // The time stops with the run
+        let game_time = simulation.get_game_time();
+        step(&mut simulation, GameAction::None);
+        assert_eq!(simulation.get_game_time(), game_time);
+
+        assert_eq!(step(&mut simulation, GameAction::PauseResume), vec![SimulationEvent::StateChanged {
+            previous: GameState::GameOver,
+            current: GameState::NotStarted,
+        }]);
+        assert!(simulation.get_bot_cars().is_empty());
+        assert_eq!(simulation.get_game_over_collision(), None);
+    }
+
+    #[test]
+    fn level_run_starts_again_after_a_pause() {
+        let level = Level::parse("bpm = 120.0\nspeed = 300.0\n\n[[cars]]\ntime = 1.0\nlane = 1\n", 3, 3).unwrap();
+        let mut simulation = new_level_simulation(Some(SEED), Some(LevelTrack::from_level(&level)));
+        step(&mut simulation, GameAction::PauseResume);
+
+        run_for(&mut simulation, 1.5);
+        assert_eq!(simulation.get_bot_cars().len(), 1);
+        assert!(simulation.get_score() > 0);
+        step(&mut simulation, GameAction::PauseResume);
+        step(&mut simulation, GameAction::PauseResume);
+
+        // The music starts again from its start, so does the whole run of the level
+        assert_eq!(simulation.get_score(), 0);
+        assert_eq!(simulation.get_game_time(), 0.0);
+        assert!(simulation.get_bot_cars().is_empty());
+        assert_eq!(simulation.get_seed(), SEED);
+        run_for(&mut simulation, 1.1);
+        assert_eq!(simulation.get_bot_cars().len(), 1);
+    }
+
+    #[test]
+    fn reloaded_hitboxes_keep_the_textures_of_the_cars_in_range() {
+        let mut simulation = new_simulation(Some(SEED));
+        step(&mut simulation, GameAction::PauseResume);
+        while simulation.get_bot_cars().iter().all(|bot_car| bot_car.get_texture_id() == 0) {
+            step(&mut simulation, GameAction::None);
+        }
+
+        simulation.set_hitboxes(CarHitboxes {
+            player_car: Hitbox::from_size(150, 73),
+            bot_cars: vec![Hitbox::from_size(158, 75)],
+        });
+        assert!(simulation.get_bot_cars().iter().all(|bot_car| bot_car.get_texture_id() == 0));
+        // The cars on the road are moved and checked with the new hitboxes
+        for _ in 0..120 {
+            step(&mut simulation, GameAction::None);
+        }
+    }
+}
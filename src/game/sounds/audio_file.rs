@@ -0,0 +1,65 @@
+use std::io::Cursor;
+
+use hound::{SampleFormat, WavReader};
+use lewton::inside_ogg::OggStreamReader;
+
+// The sound decoder of macroquad panics on a broken file, the format is checked before
+pub fn is_wav(bytes: &[u8]) -> bool {
+    bytes.len() > 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE"
+}
+
+pub fn is_ogg(bytes: &[u8]) -> bool {
+    bytes.len() > 4 && &bytes[0..4] == b"OggS"
+}
+
+// Duration of a wav or ogg file in seconds, an ogg file is decoded to count its samples
+pub fn get_duration(bytes: &[u8]) -> Result<f32, String> {
+    if is_wav(bytes) {
+        let reader = WavReader::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        Ok(reader.duration() as f32 / reader.spec().sample_rate as f32)
+    } else if is_ogg(bytes) {
+        let mut reader = OggStreamReader::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        let sample_rate = reader.ident_hdr.audio_sample_rate;
+        let channels = reader.ident_hdr.audio_channels;
+
+        // The packets interleave the samples of the channels
+        let mut sample_count = 0;
+        while let Some(packet) = reader.read_dec_packet_itl().map_err(|e| e.to_string())? {
+            sample_count += packet.len();
+        }
+        Ok(sample_count as f32 / channels as f32 / sample_rate as f32)
+    } else {
+        Err("not a wav or ogg file".to_string())
+    }
+}
+
+// Samples of a wav or ogg file between -1 and 1, the channels are mixed in one. Returns the samples and the sample rate
+pub fn decode(bytes: &[u8]) -> Result<(Vec<f32>, u32), String> {
+    let (samples, channels, sample_rate) = if is_wav(bytes) {
+        let reader = WavReader::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<Vec<f32>, _>>(),
+            SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader.into_samples::<i32>().map(|sample| sample.map(|sample| sample as f32 / scale)).collect()
+            }
+        }.map_err(|e| e.to_string())?;
+        (samples, spec.channels as usize, spec.sample_rate)
+    } else if is_ogg(bytes) {
+        let mut reader = OggStreamReader::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        let mut samples = Vec::new();
+        while let Some(packet) = reader.read_dec_packet_itl().map_err(|e| e.to_string())? {
+            samples.extend(packet.into_iter().map(|sample| sample as f32 / 32768.0));
+        }
+        (samples, reader.ident_hdr.audio_channels as usize, reader.ident_hdr.audio_sample_rate)
+    } else {
+        return Err("not a wav or ogg file".to_string());
+    };
+
+    let channels = channels.max(1);
+    let mono_samples = samples.chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    Ok((mono_samples, sample_rate))
+}
@@ -1,2 +1,3 @@
+pub mod audio_file;
 pub mod sounds_manager;
 mod rusty_sound;
\ No newline at end of file
@@ -1,8 +1,6 @@
 use macroquad::audio;
 use macroquad::audio::Sound;
 
-use crate::utils::rusty_error::RustyResult;
-
 pub struct RustySound {
     pub sound: Sound,
     pub volume: f32,
@@ -10,13 +8,12 @@ pub struct RustySound {
 }
 
 impl RustySound {
-    pub async fn new(sound_path: &str, sound_volume: f32) -> RustyResult<RustySound> {
-        let sound = audio::load_sound(sound_path).await?;
+    pub fn new(sound: Sound, sound_volume: f32) -> RustySound {
         audio::set_sound_volume(sound, sound_volume);
-        Ok(RustySound {
+        RustySound {
             sound,
             volume: sound_volume,
             playing_status: false,
-        })
+        }
     }
-}
\ No newline at end of file
+}
@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use macroquad::audio;
 
-use crate::config::SOUND_FILE_FOR_SOUND_TYPE;
+use crate::config::config_file::SoundsConfig;
+use crate::config::SOUND_ASSET_FOR_SOUND_TYPE;
+use crate::game::assets::asset_manager::AssetManager;
 use crate::game::sounds::rusty_sound::RustySound;
 use crate::utils::rusty_error::RustyResult;
 
@@ -20,20 +22,31 @@ pub struct SoundsManager {
 }
 
 impl SoundsManager {
-    pub async fn new() -> RustyResult<SoundsManager> {
+    pub fn new(sounds_config: &SoundsConfig, assets: &AssetManager) -> RustyResult<SoundsManager> {
         let mut sounds: HashMap<SoundType, RustySound> = HashMap::new();
-        for &(sound_type, sound_file, sound_volume) in &SOUND_FILE_FOR_SOUND_TYPE {
+        for (sound_type, sound_config) in sounds_config.get_sounds() {
             // Save the sound type and the sound object in a hashmap
             // to be able to find the sound object by the sound type
-            sounds.insert(sound_type, RustySound::new(sound_file, sound_volume).await?);
+            let sound = assets.get_sound(SoundsManager::get_asset_name(sound_type))?;
+            sounds.insert(sound_type, RustySound::new(sound, sound_config.volume));
         }
 
         Ok(SoundsManager {
             sounds,
-            sounds_muted: false,
+            sounds_muted: sounds_config.muted,
         })
     }
 
+    pub fn get_asset_name(sound_type: SoundType) -> &'static str {
+        let mut asset_name = "";
+        for &(default_type, name, _) in &SOUND_ASSET_FOR_SOUND_TYPE {
+            if default_type == sound_type {
+                asset_name = name;
+            }
+        }
+        asset_name
+    }
+
     pub fn play_sound(&mut self, sound_type: SoundType, play_loop: bool) {
         // Get the sound object from the hashmap by the sound type
         let sound = self.sounds.get_mut(&(sound_type));
@@ -63,9 +76,16 @@ impl SoundsManager {
         }
     }
 
+    pub fn stop_all_sounds(&mut self) {
+        for sound in self.sounds.values_mut() {
+            sound.playing_status = false;
+            audio::stop_sound(sound.sound);
+        }
+    }
+
     pub fn set_mute_songs(&mut self) {
+        self.sounds_muted = !self.sounds_muted;
         for sound in self.sounds.values_mut() {
-            self.sounds_muted = !self.sounds_muted;
             // If the sounds are not muted, the volume is the original volume if the sound is playing
             if self.sounds_muted {
                 audio::set_sound_volume(sound.sound, 0.0);
@@ -74,4 +94,8 @@ impl SoundsManager {
             }
         }
     }
-}
\ No newline at end of file
+
+    pub fn is_muted(&self) -> bool {
+        self.sounds_muted
+    }
+}
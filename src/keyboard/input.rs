@@ -0,0 +1,36 @@
+use gilrs::{Axis, Button, Gilrs};
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum AxisDirection {
+    Negative,
+    Positive,
+}
+
+/// Something of a gamepad the player can press to do an action: a button or a direction of a stick.
+/// The keys of the keyboard are received as events by the input queue
+#[derive(PartialEq, Copy, Clone)]
+pub enum Input {
+    GamepadButton(Button),
+    GamepadAxis(Axis, AxisDirection),
+}
+
+impl Input {
+    // An input is down if it is down on any connected gamepad,
+    // a stick is down when it is pushed beyond the dead zone
+    pub fn is_down(&self, gilrs: &Gilrs, dead_zone: f32) -> bool {
+        match *self {
+            Input::GamepadButton(button) => {
+                gilrs.gamepads().any(|(_, gamepad)| gamepad.is_pressed(button))
+            }
+            Input::GamepadAxis(axis, direction) => {
+                gilrs.gamepads().any(|(_, gamepad)| {
+                    let value = gamepad.value(axis);
+                    match direction {
+                        AxisDirection::Negative => value < -dead_zone,
+                        AxisDirection::Positive => value > dead_zone,
+                    }
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+use gilrs::Gilrs;
+use log::warn;
+use macroquad::input::KeyCode;
+use macroquad::input::utils::{register_input_subscriber, repeat_all_miniquad_input};
+use macroquad::miniquad::{Context, EventHandler, KeyMods};
+use macroquad::time::get_time;
+
+use crate::config::config_file::GamepadConfig;
+use crate::config::GAMEPAD_GAME;
+use crate::keyboard::{GameAction, get_game_action_from_key_code};
+use crate::keyboard::key_game::KeyGame;
+
+#[derive(Clone, Copy)]
+pub struct InputEvent {
+    pub action: GameAction,
+    // Seconds since the start of the game, on the clock of `get_time`
+    pub time: f64,
+}
+
+/// Actions pressed by the player, collected on the main thread at each frame
+/// and kept in the order they were pressed until the game consumes them
+pub struct InputQueue {
+    key_bindings: Vec<(KeyCode, GameAction)>,
+    // Index given by macroquad to receive all the keyboard events of the frame
+    input_subscriber: usize,
+    // Without gamepad support (disabled, no driver, no permission...) the keyboard still works
+    gilrs: Option<Gilrs>,
+    gamepad_inputs: Vec<(KeyGame, GameAction)>,
+    dead_zone: f32,
+    events: VecDeque<InputEvent>,
+}
+
+// Receives the keyboard events of the frame in their order,
+// `is_key_pressed` only tells which keys were pressed during the frame
+struct KeyboardEvents {
+    keys: Vec<KeyCode>,
+}
+
+impl EventHandler for KeyboardEvents {
+    fn update(&mut self, _ctx: &mut Context) {}
+
+    fn draw(&mut self, _ctx: &mut Context) {}
+
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods, repeat: bool) {
+        // A key held down is only one press
+        if !repeat {
+            self.keys.push(keycode);
+        }
+    }
+}
+
+impl InputQueue {
+    pub fn new(key_bindings: Vec<(KeyCode, GameAction)>, gamepad_config: &GamepadConfig) -> InputQueue {
+        let mut gilrs: Option<Gilrs> = None;
+        if gamepad_config.enabled {
+            match Gilrs::new() {
+                Ok(new_gilrs) => gilrs = Some(new_gilrs),
+                Err(e) => warn!("Error initializing gamepads: {}", e),
+            }
+        }
+
+        let mut gamepad_inputs = Vec::new();
+        if gilrs.is_some() {
+            for &(input, action) in GAMEPAD_GAME.iter() {
+                gamepad_inputs.push((KeyGame::new(input), action));
+            }
+        }
+
+        InputQueue {
+            key_bindings,
+            input_subscriber: register_input_subscriber(),
+            gilrs,
+            gamepad_inputs,
+            dead_zone: gamepad_config.dead_zone,
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn set_key_bindings(&mut self, key_bindings: Vec<(KeyCode, GameAction)>) {
+        self.key_bindings = key_bindings;
+    }
+
+    pub fn set_dead_zone(&mut self, dead_zone: f32) {
+        self.dead_zone = dead_zone;
+    }
+
+    // Must be called once per frame, before reading the events
+    pub fn update(&mut self) {
+        let frame_time = get_time();
+        let mut new_events: Vec<InputEvent> = Vec::new();
+
+        let mut keyboard_events = KeyboardEvents {
+            keys: Vec::new(),
+        };
+        repeat_all_miniquad_input(&mut keyboard_events, self.input_subscriber);
+
+        // The keyboard events have no time, they are given the time of the frame
+        for key in keyboard_events.keys {
+            if let Some(action) = get_game_action_from_key_code(&self.key_bindings, key) {
+                new_events.push(InputEvent {
+                    action,
+                    time: frame_time,
+                });
+            }
+        }
+
+        if let Some(gilrs) = self.gilrs.as_mut() {
+            // The state of the gamepads is updated event by event, a short tap between two frames is not missed
+            while let Some(event) = gilrs.next_event() {
+                let age = SystemTime::now().duration_since(event.time).map_or(0.0, |age| age.as_secs_f64());
+
+                for (gamepad_input, action) in self.gamepad_inputs.iter_mut() {
+                    let is_down = gamepad_input.input.is_down(gilrs, self.dead_zone);
+                    gamepad_input.update(is_down);
+
+                    if gamepad_input.is_key_pressed() {
+                        new_events.push(InputEvent {
+                            action: *action,
+                            time: frame_time - age,
+                        });
+                    }
+                }
+            }
+        }
+
+        // The sort is stable, the events of the same time keep their order
+        new_events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.events.extend(new_events);
+    }
+
+    // Gives the oldest action not consumed yet
+    pub fn pop_event(&mut self) -> Option<InputEvent> {
+        self.events.pop_front()
+    }
+
+    // The pending actions are forgotten, they were meant for a screen which has been left
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
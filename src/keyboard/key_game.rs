@@ -1,16 +1,16 @@
-use macroquad::input::KeyCode;
+use crate::keyboard::input::Input;
 
 #[derive(Copy, Clone)] // Ajoutez cette dérivation pour le trait Copy
 pub struct KeyGame {
-    pub key: KeyCode,
+    pub input: Input,
     pressed: bool,
     down: bool,
 }
 
 impl KeyGame {
-    pub fn new(key: KeyCode) -> Self {
+    pub fn new(input: Input) -> Self {
         Self {
-            key,
+            input,
             pressed: false,
             down: false,
         }
@@ -24,4 +24,4 @@ impl KeyGame {
     pub fn is_key_pressed(&self) -> bool {
         self.pressed
     }
-}
\ No newline at end of file
+}
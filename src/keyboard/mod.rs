@@ -1,25 +1,28 @@
 use macroquad::input::KeyCode;
 
-use crate::config::KEY_GAME;
-
+pub mod input;
+pub mod input_queue;
 pub mod key_game;
-pub mod keyboard_observer;
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum GameAction {
     Up,
     Down,
     Mute,
     PauseResume,
     Quit,
+    HighScores,
+    Settings,
     None,
 }
 
-pub const GAME_ACTION: [(GameAction, &str); 5] = [
+pub const GAME_ACTION: [(GameAction, &str); 7] = [
     (GameAction::Up, "Go Up: "),
     (GameAction::Down, "Go Down: "),
     (GameAction::Mute, "Mute: "),
     (GameAction::PauseResume, "Pause and resume: "),
+    (GameAction::HighScores, "High scores: "),
+    (GameAction::Settings, "Settings: "),
     (GameAction::Quit, "Quit: ")
 ];
 
@@ -154,27 +157,32 @@ pub fn get_action_description_from_game_action(game_action: GameAction) -> Optio
             return_value = Some(description.to_string());
         }
     }
-    return return_value;
+    return_value
 }
 
-fn get_game_action_from_key_code(key_code: KeyCode) -> Option<GameAction> {
+pub fn get_game_action_from_key_code(key_bindings: &[(KeyCode, GameAction)], key_code: KeyCode) -> Option<GameAction> {
     let mut return_value: Option<GameAction> = None;
-    for &(code, action) in &KEY_GAME {
+    for &(code, action) in key_bindings {
         if code == key_code {
             return_value = Some(action);
         }
     }
-    return return_value;
+    return_value
+}
+
+// First key of the action, the one shown in the messages
+pub fn get_key_code_from_game_action(key_bindings: &[(KeyCode, GameAction)], game_action: GameAction) -> Option<KeyCode> {
+    get_key_codes_from_game_action(key_bindings, game_action).first().copied()
 }
 
-pub fn get_key_code_from_game_action(game_action: GameAction) -> Option<KeyCode> {
-    let mut return_value: Option<KeyCode> = None;
-    for &(code, action) in &KEY_GAME {
+pub fn get_key_codes_from_game_action(key_bindings: &[(KeyCode, GameAction)], game_action: GameAction) -> Vec<KeyCode> {
+    let mut return_value: Vec<KeyCode> = Vec::new();
+    for &(code, action) in key_bindings {
         if action == game_action {
-            return_value = Some(code);
+            return_value.push(code);
         }
     }
-    return return_value;
+    return_value
 }
 
 pub fn get_str_from_key_code(key_code: KeyCode) -> String {
@@ -185,4 +193,14 @@ pub fn get_str_from_key_code(key_code: KeyCode) -> String {
         }
     };
     result
-}
\ No newline at end of file
+}
+
+pub fn get_key_code_from_str(name: &str) -> Option<KeyCode> {
+    let mut result: Option<KeyCode> = None;
+    for (key, value) in KEY_CODE_WITH_STRING.iter() {
+        if value.eq_ignore_ascii_case(name) {
+            result = Some(*key);
+        }
+    }
+    result
+}
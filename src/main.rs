@@ -1,12 +1,24 @@
-use std::sync::mpsc;
+use std::path::Path;
 
+use clap::Parser;
+use log::{error, LevelFilter};
 use macroquad::prelude::*;
 
-use crate::game::game::Game;
-use crate::keyboard::GameAction;
-use crate::keyboard::keyboard_observer::KeyboardObserver;
+use crate::arguments::{Arguments, Command};
+use crate::config::config_file::Config;
+use crate::game::assets::manifest::AssetManifest;
+use crate::game::benchmark::run_benchmark;
+use crate::game::car::hitbox::CarHitboxes;
+use crate::game::game::{Game, GameOptions};
+use crate::game::graphics::error_screen::show_error_screen;
+use crate::game::replay::Replay;
+use crate::game::rhythm::beat_analysis::write_beat_map;
+use crate::game::rhythm::level::{Level, write_level};
+use crate::utils::crash_log::write_crash_log;
+use crate::utils::logger::init_logger;
 use crate::utils::rusty_error::RustyResult;
 
+mod arguments;
 mod game;
 mod keyboard;
 mod utils;
@@ -17,30 +29,144 @@ pub const WINDOW_WIDTH: f32 = 1280.0;
 pub const WINDOW_HEIGHT: f32 = 720.0;
 pub const GAME_NAME: &str = "Rusty Corks";
 
-#[macroquad::main(window_conf())]
-async fn main() -> RustyResult<()> {
-    let mut quit_game = false;
-    let (sender, receiver) = mpsc::channel::<GameAction>();
-    let mut observer = KeyboardObserver::new(sender);
+const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+// The window is only opened when the game is played, the benchmark and the commands run without it
+fn main() {
+    init_logger(DEFAULT_LOG_LEVEL);
+
+    // Clap prints the help or the argument errors and exits
+    let arguments = Arguments::parse();
+    if let Some(log_level) = arguments.log_level {
+        log::set_max_level(log_level);
+    }
+
+    let result = match (&arguments.command, arguments.benchmark) {
+        (Some(Command::Analyze { music, output }), _) => start_analysis(music, output.as_deref()),
+        (Some(Command::CreateLevel { music, output }), _) => start_level_creation(arguments.config.as_deref(), music, output.as_deref()),
+        (None, Some(runs)) => start_benchmark(arguments, runs),
+        (None, None) => {
+            macroquad::Window::from_config(window_conf(&arguments), run_window(arguments));
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+}
 
-    observer.start_observer();
-    let mut game = Game::new(receiver).await?;
+async fn run_window(arguments: Arguments) {
+    let fullscreen = arguments.fullscreen;
+
+    // The error is shown in the window, the player may not have started the game from a terminal
+    if let Err(e) = start_game(arguments).await {
+        error!("{}", e);
+
+        let crash_log_path = write_crash_log(&e).map_err(|log_error| error!("{}", log_error)).ok();
+        show_error_screen(&e, crash_log_path.as_deref(), fullscreen).await;
+
+        std::process::exit(1);
+    }
+}
+
+async fn start_game(arguments: Arguments) -> RustyResult<()> {
+    let (config, config_path, options) = get_settings(arguments)?;
+
+    let mut quit_game = false;
+    let mut game = Game::new(config, config_path, options).await?;
 
     while !quit_game {
-        quit_game = game.run().await?;
+        quit_game = game.run()?;
+        if game.is_asset_reload_requested() {
+            game.reload_assets().await;
+        }
         next_frame().await;
     }
 
-    observer.stop_observer();
     Ok(())
 }
 
-pub fn window_conf() -> Conf {
+fn start_benchmark(arguments: Arguments, runs: u32) -> RustyResult<()> {
+    let (config, _, options) = get_settings(arguments)?;
+    run_benchmark(&config, options, runs)
+}
+
+// The commands print their result, the logs only show the problems
+fn start_analysis(music: &Path, output: Option<&Path>) -> RustyResult<()> {
+    let (beat_map, output) = write_beat_map(music, output)?;
+    println!("{}: {} BPM, first beat at {} s, {} beats, {} sections, written in {}",
+             music.display(),
+             beat_map.bpm,
+             beat_map.beats[0],
+             beat_map.beats.len(),
+             beat_map.sections.len(),
+             output.display());
+    Ok(())
+}
+
+// The level is written for the road of the configuration
+fn start_level_creation(config_path: Option<&Path>, music: &Path, output: Option<&Path>) -> RustyResult<()> {
+    let config_path = config_path.map_or_else(Config::get_default_path, Path::to_path_buf);
+    let config = Config::load(&config_path)?;
+    let (level, output) = write_level(music, output, config.game.lanes.len())?;
+    println!("{}: {} cars at {} BPM, written in {}",
+             music.display(),
+             level.cars.len(),
+             level.bpm,
+             output.display());
+    Ok(())
+}
+
+fn get_settings(arguments: Arguments) -> RustyResult<(Config, std::path::PathBuf, GameOptions)> {
+    let config_path = arguments.config.unwrap_or_else(Config::get_default_path);
+    let config = Config::load(&config_path)?;
+    let replay = match &arguments.replay {
+        Some(replay_path) => Some(Replay::load(replay_path)?),
+        None => None,
+    };
+    // The lanes and the cars of the level are checked with the road of the configuration and the bot cars of the assets
+    let level = match &arguments.level {
+        Some(level_path) => {
+            let asset_pack = arguments.asset_pack.as_deref().or(config.assets.pack.as_deref());
+            // The game starts with the default assets when the pack of the configuration is broken
+            let bot_car_count = match get_bot_car_count(&config, asset_pack) {
+                Err(_) if arguments.asset_pack.is_none() && asset_pack.is_some() => get_bot_car_count(&config, None)?,
+                bot_car_count => bot_car_count?,
+            };
+            Some(Level::load(level_path, config.game.lanes.len(), bot_car_count)?)
+        }
+        None => None,
+    };
+
+    let options = GameOptions {
+        seed: arguments.seed,
+        replay,
+        level,
+        asset_pack: arguments.asset_pack,
+        start_speed: arguments.start_speed,
+        mode: arguments.mode,
+        mute: arguments.mute,
+        fullscreen: arguments.fullscreen,
+        dev_mode: arguments.dev,
+    };
+
+    Ok((config, config_path, options))
+}
+
+fn get_bot_car_count(config: &Config, asset_pack: Option<&str>) -> RustyResult<usize> {
+    let (manifest, pack) = AssetManifest::load_for_game(config, asset_pack, None)?;
+    Ok(CarHitboxes::load(&manifest, pack.as_ref())?.bot_cars.len())
+}
+
+pub fn window_conf(arguments: &Arguments) -> Conf {
     Conf {
         window_title: GAME_NAME.to_string(),
-        window_width: WINDOW_WIDTH as i32,
-        window_height: WINDOW_HEIGHT as i32,
-        window_resizable: false,
+        window_width: arguments.width as i32,
+        window_height: arguments.height as i32,
+        fullscreen: arguments.fullscreen,
+        window_resizable: true,
         ..Default::default()
     }
-}
\ No newline at end of file
+}
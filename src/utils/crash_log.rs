@@ -0,0 +1,36 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::GAME_NAME;
+use crate::utils::data_directory::get_data_directory;
+use crate::utils::rusty_error::{RustyError, RustyResult};
+
+const CRASH_LOG_FILE_NAME: &str = "crash.log";
+
+// Appends the error which stopped the game to the crash log of the user data directory,
+// with what is needed to reproduce it. The path is returned to be shown to the player
+pub fn write_crash_log(error: &RustyError) -> RustyResult<PathBuf> {
+    let directory = get_data_directory();
+    let path = directory.join(CRASH_LOG_FILE_NAME);
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let report = format!("[{}] {} {} ({} {})\nArguments: {}\n{}\n\n",
+                         timestamp,
+                         GAME_NAME,
+                         env!("CARGO_PKG_VERSION"),
+                         std::env::consts::OS,
+                         std::env::consts::ARCH,
+                         arguments.join(" "),
+                         error);
+
+    fs::create_dir_all(&directory).map_err(RustyError::file("create the data directory", &directory))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)
+        .map_err(RustyError::file("open the crash log", &path))?;
+    file.write_all(report.as_bytes()).map_err(RustyError::file("write the crash log", &path))?;
+
+    Ok(path)
+}
@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+const DATA_DIRECTORY_NAME: &str = "rusty_corks";
+
+// Directory of the user data (high scores...), for example ~/.local/share/rusty_corks on Linux.
+// If the system has no data directory, the files are saved next to the game
+pub fn get_data_directory() -> PathBuf {
+    match dirs::data_dir() {
+        Some(data_directory) => data_directory.join(DATA_DIRECTORY_NAME),
+        None => PathBuf::from("."),
+    }
+}
+
+// Directory of the user configuration, for example ~/.config/rusty_corks on Linux
+pub fn get_config_directory() -> PathBuf {
+    match dirs::config_dir() {
+        Some(config_directory) => config_directory.join(DATA_DIRECTORY_NAME),
+        None => PathBuf::from("."),
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Detects the changes of files and directories by comparing their modification times,
+/// the directories are watched with all their files
+pub struct FileWatcher {
+    paths: Vec<PathBuf>,
+    modification_times: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new(paths: Vec<PathBuf>) -> FileWatcher {
+        let modification_times = FileWatcher::get_modification_times(&paths);
+
+        FileWatcher {
+            paths,
+            modification_times,
+        }
+    }
+
+    // True if a file has been modified, added or removed since the last call
+    pub fn has_changed(&mut self) -> bool {
+        let modification_times = FileWatcher::get_modification_times(&self.paths);
+        let changed = modification_times != self.modification_times;
+        self.modification_times = modification_times;
+        changed
+    }
+
+    fn get_modification_times(paths: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+        let mut modification_times = HashMap::new();
+        for path in paths {
+            FileWatcher::add_modification_times(path, &mut modification_times);
+        }
+        modification_times
+    }
+
+    // A missing path is not an error, the change is seen when it is created
+    fn add_modification_times(path: &Path, modification_times: &mut HashMap<PathBuf, SystemTime>) {
+        if path.is_dir() {
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    FileWatcher::add_modification_times(&entry.path(), modification_times);
+                }
+            }
+        } else if let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            modification_times.insert(path.to_path_buf(), modified);
+        }
+    }
+}
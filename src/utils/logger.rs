@@ -0,0 +1,141 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::utils::data_directory::get_data_directory;
+
+const LOG_DIRECTORY: &str = "logs";
+const LOG_FILE_NAME: &str = "rusty_corks.log";
+// The file is rotated beyond this size, the previous files are kept as rusty_corks.log.1, .2...
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+const OLD_LOG_FILE_COUNT: usize = 3;
+// The messages of the libraries are only shown from this level, the verbosity only applies to the game
+const LIBRARY_LOG_LEVEL: Level = Level::Warn;
+
+static LOGGER: RustyLogger = RustyLogger {
+    log_file: Mutex::new(None),
+};
+
+/// Writes the messages of the `log` macros in the terminal and in a rotating file
+/// of the user data directory, so the players can send it when something goes wrong
+struct RustyLogger {
+    // None until the file is opened, or if it cannot be opened
+    log_file: Mutex<Option<LogFile>>,
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+// Installs the logger with the default verbosity, it can be changed later with `log::set_max_level`
+pub fn init_logger(level: LevelFilter) {
+    if log::set_logger(&LOGGER).is_err() {
+        return;
+    }
+    log::set_max_level(level);
+
+    let directory = get_data_directory().join(LOG_DIRECTORY);
+    match LogFile::open(&directory) {
+        Ok(log_file) => {
+            if let Ok(mut guard) = LOGGER.log_file.lock() {
+                *guard = Some(log_file);
+            }
+        }
+        // The messages are still written in the terminal
+        Err(e) => log::warn!("Impossible to open the log file in {}: {}", directory.display(), e),
+    }
+}
+
+pub fn get_log_file_path() -> PathBuf {
+    get_data_directory().join(LOG_DIRECTORY).join(LOG_FILE_NAME)
+}
+
+impl Log for RustyLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let is_game = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+        metadata.level() <= log::max_level() && (is_game || metadata.level() <= LIBRARY_LOG_LEVEL)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let line = format!("[{}.{:03}] {:<5} {}: {}\n",
+                           time.as_secs(),
+                           time.subsec_millis(),
+                           record.level(),
+                           record.target(),
+                           record.args());
+
+        eprint!("{}", line);
+
+        // A poisoned lock only means another thread panicked while logging, the file is still usable
+        let mut guard = self.log_file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(log_file) = guard.as_mut() {
+            log_file.write(&line);
+        }
+    }
+
+    fn flush(&self) {
+        let mut guard = self.log_file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(log_file) = guard.as_mut() {
+            let _ = log_file.file.flush();
+        }
+    }
+}
+
+impl LogFile {
+    fn open(directory: &Path) -> std::io::Result<LogFile> {
+        fs::create_dir_all(directory)?;
+        let path = directory.join(LOG_FILE_NAME);
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        let mut log_file = LogFile {
+            path,
+            file,
+            size,
+        };
+        if log_file.size >= MAX_LOG_FILE_SIZE {
+            log_file.rotate()?;
+        }
+
+        Ok(log_file)
+    }
+
+    // A failed write is ignored, logging must never stop the game
+    fn write(&mut self, line: &str) {
+        if self.size + line.len() as u64 > MAX_LOG_FILE_SIZE && self.rotate().is_err() {
+            return;
+        }
+
+        if self.file.write_all(line.as_bytes()).is_ok() {
+            self.size += line.len() as u64;
+        }
+    }
+
+    // The oldest file is removed and each file gets the next number
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let old_path = |index: usize| PathBuf::from(format!("{}.{}", self.path.display(), index));
+
+        let _ = fs::remove_file(old_path(OLD_LOG_FILE_COUNT));
+        for index in (1..OLD_LOG_FILE_COUNT).rev() {
+            let _ = fs::rename(old_path(index), old_path(index + 1));
+        }
+        fs::rename(&self.path, old_path(1))?;
+
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
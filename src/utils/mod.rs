@@ -1,2 +1,6 @@
-pub mod timer;
-pub mod rusty_error;
\ No newline at end of file
+pub mod crash_log;
+pub mod data_directory;
+pub mod file_watcher;
+pub mod logger;
+pub mod rusty_error;
+pub mod stable_hasher;
\ No newline at end of file
@@ -1,46 +1,44 @@
 use std::error;
 use std::fmt;
 use std::fmt::{Debug, Display};
-use std::sync::mpsc::TryRecvError;
-
-use macroquad::prelude::FileError;
+use std::path::PathBuf;
 
 // Definition of a custom result alias to simplify error handling.
 pub type RustyResult<T> = Result<T, RustyError>;
 
-// Definition of a custom locking error with an associated message.
-// This error is used in the game to handle mutex locking error.
-#[derive(Debug)]
-pub struct LockError {
-    pub message: String,
-}
-
-// Definition of an error related to directory reading with an associated message.
-#[derive(Debug)]
-pub struct ReadDirectoryError {
-    pub message: String,
-}
-
 // Custom error type, to simplify error handling
-// It is a wrapper around the different error types
+// Each variant tells which part of the game failed, with enough context to fix the problem
 #[derive(Debug)]
 pub enum RustyError {
-    RustyLock(LockError),
-    File(FileError),
-    Recv(TryRecvError),
-    ReadDirectory(std::io::Error),
-    LaneNotFound,
+    // A file or a directory could not be read or written, `action` tells what the game was doing
+    // ("read the replay", "create the high score directory"...)
+    File { action: &'static str, path: PathBuf, source: std::io::Error },
+    Replay(String),
+    HighScore(String),
+    Config(String),
+    Asset(String),
+    BeatMap(String),
+    Level(String),
 }
 
 // Implementation of the `Display` trait to display errors in a user-friendly way.
 impl Display for RustyError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            RustyError::RustyLock(e) =>
-                write!(f, "Rusty lock error: {}", e.message),
-            RustyError::LaneNotFound =>
-                write!(f, "Lane not found"),
-            _ => Ok(()),
+            RustyError::File { action, path, source } =>
+                write!(f, "Impossible to {} {}: {}", action, path.display(), source),
+            RustyError::Replay(message) =>
+                write!(f, "Replay error: {}", message),
+            RustyError::HighScore(message) =>
+                write!(f, "High score error: {}", message),
+            RustyError::Config(message) =>
+                write!(f, "Configuration error: {}", message),
+            RustyError::Asset(message) =>
+                write!(f, "Asset error: {}", message),
+            RustyError::BeatMap(message) =>
+                write!(f, "Beat map error: {}", message),
+            RustyError::Level(message) =>
+                write!(f, "Level error: {}", message),
         }
     }
 }
@@ -49,30 +47,21 @@ impl Display for RustyError {
 impl error::Error for RustyError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            RustyError::RustyLock(_) => None,
-            RustyError::File(ref e) => Some(e),
-            RustyError::Recv(ref e) => Some(e),
-            RustyError::ReadDirectory(ref e) => Some(e),
-            RustyError::LaneNotFound => None,
+            RustyError::File { ref source, .. } => Some(source),
+            RustyError::Replay(_) => None,
+            RustyError::HighScore(_) => None,
+            RustyError::Config(_) => None,
+            RustyError::Asset(_) => None,
+            RustyError::BeatMap(_) => None,
+            RustyError::Level(_) => None,
         }
     }
 }
 
-// Conversion of various errors into `RustyError` using `From` implementations.
-impl From<FileError> for RustyError {
-    fn from(err: FileError) -> RustyError {
-        RustyError::File(err)
-    }
-}
-
-impl From<TryRecvError> for RustyError {
-    fn from(err: TryRecvError) -> RustyError {
-        RustyError::Recv(err)
+impl RustyError {
+    // Builds the error of a failed file operation, to be used with `map_err`
+    pub fn file(action: &'static str, path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> RustyError {
+        let path = path.into();
+        move |source| RustyError::File { action, path, source }
     }
 }
-
-impl From<std::io::Error> for RustyError {
-    fn from(err: std::io::Error) -> RustyError {
-        RustyError::ReadDirectory(err)
-    }
-}
\ No newline at end of file